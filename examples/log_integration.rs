@@ -20,8 +20,8 @@ impl BugsnagLogger {
         log::set_logger(|max_level| {
             max_level.set(log::LogLevelFilter::Info);
             Box::new(BugsnagLogger {
-                api: api,
-                max_loglevel: max_loglevel,
+                api,
+                max_loglevel,
             })
         })
     }
@@ -42,12 +42,21 @@ impl log::Log for BugsnagLogger {
     }
 
     fn log(&self, record: &LogRecord) {
+        let level_str = record.metadata().level().to_string();
+        let message = record.args().to_string();
+
         if self.enabled(record.metadata()) {
             let level = convert_log_level(record.metadata().level());
-            let level_str = record.metadata().level().to_string();
-            let message = record.args().to_string();
 
-            self.api.notify(&level_str, &message).severity(level);
+            self.api
+                .notify(&level_str, &message)
+                .severity(level)
+                .severity_reason(bugsnag::SeverityReason::Log(level_str.to_lowercase()));
+        } else {
+            // messages that are not reported are kept as breadcrumbs
+            let mut breadcrumb = bugsnag::Breadcrumb::new(&message, bugsnag::BreadcrumbType::Log);
+            let _ = breadcrumb.set_metadata("level", &level_str);
+            self.api.leave_breadcrumb(breadcrumb);
         }
     }
 }
@@ -64,9 +73,12 @@ fn main() {
     // initialize the logger
     BugsnagLogger::init(api, LogLevel::Warn).unwrap();
 
-    // the following two messages should not show up in bugsnag, because
-    // we set the maximum log level to errors
+    // the following message should not show up in bugsnag, because the
+    // logger filters debug messages
     debug!("Hello this is a debug message!");
+
+    // the following message should only show up as breadcrumb, because
+    // we set the maximum log level to warnings
     info!("Hello this is a info message!");
 
     // the following two should be send to bugsnag
@@ -61,7 +61,7 @@ fn register_panic_handler_with_global_instance(api: bugsnag::Bugsnag) {
             if let Ok(api) = api_mtx.lock() {
                 if bugsnag::panic::handle(
                     &api,
-                    &info,
+                    info,
                     Some(&["register_panic_handler_with_global_instance"]),
                 ).is_err()
                 {
@@ -11,7 +11,7 @@ use std::panic;
 /// the object are possible
 fn register_panic_handler(api: bugsnag::Bugsnag) {
     panic::set_hook(Box::new(move |info| {
-        if bugsnag::panic::handle(&api, &info, Some(&["register_panic_handler"])).is_err() {
+        if bugsnag::panic::handle(&api, info, Some(&["register_panic_handler"])).is_err() {
             println!("Error at notifying bugsnag!");
         }
     }));
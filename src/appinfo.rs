@@ -1,4 +1,4 @@
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AppInfo {
     #[serde(skip_serializing_if = "Option::is_none")] version: Option<String>,
@@ -16,6 +16,10 @@ impl AppInfo {
             atype: atype.map_or_else(|| None, |v| Some(v.to_owned())),
         }
     }
+
+    pub fn release_stage(&self) -> Option<&str> {
+        self.release_stage.as_deref()
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,59 @@
+//! Resolves the stacktraces of notifications, that were stored with a deferred
+//! symbolication, with the debug file of the binary that created them.
+//!
+//! The notifications are given as files or as directories, e.g. the outbox of the
+//! application. The files are replaced with the symbolicated notifications. In a
+//! directory, only the unresolved notifications are symbolicated, which are then
+//! delivered by the application.
+
+extern crate bugsnag;
+
+use bugsnag::Symbolicator;
+use std::env;
+use std::path::Path;
+use std::process;
+
+const USAGE: &str =
+    "usage: bugsnag-symbolicate <debug-file> [--project-root <dir>] <notification or dir>...";
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let debug_file = args.next().unwrap_or_else(|| fail(USAGE));
+
+    let mut project_root = None;
+    let mut paths = Vec::new();
+    while let Some(arg) = args.next() {
+        if arg == "--project-root" {
+            project_root = Some(args.next().unwrap_or_else(|| fail(USAGE)));
+        } else {
+            paths.push(arg);
+        }
+    }
+    if paths.is_empty() {
+        fail(USAGE);
+    }
+
+    let mut symbolicator =
+        Symbolicator::new(&debug_file).unwrap_or_else(|err| fail(&err.to_string()));
+    if let Some(dir) = project_root {
+        symbolicator = symbolicator.project_root(&dir);
+    }
+
+    for path in paths {
+        let result = if Path::new(&path).is_dir() {
+            symbolicator.symbolicate_dir(&path)
+        } else {
+            symbolicator.symbolicate_file(&path).map(|_| 1)
+        };
+
+        match result {
+            Ok(count) => println!("{}: symbolicated {} notification(s)", path, count),
+            Err(err) => fail(&format!("{}: {}", path, err)),
+        }
+    }
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1)
+}
@@ -0,0 +1,216 @@
+//! Module for the breadcrumbs, the trail of events that led up to an error.
+
+use std::collections::VecDeque;
+use std::sync::{Mutex, MutexGuard};
+
+use serde::Serialize;
+use serde_json::{self, Map, Value};
+
+use super::{timestamp, Error};
+
+/// The default number of breadcrumbs that are stored.
+pub const DEFAULT_MAX_BREADCRUMBS: usize = 25;
+
+/// The type of a breadcrumb. The Bugsnag web interface uses the type to
+/// display the breadcrumb.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreadcrumbType {
+    Manual,
+    Log,
+    Navigation,
+    Request,
+    State,
+    Process,
+    Error,
+}
+
+/// A single breadcrumb.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Breadcrumb {
+    timestamp: String,
+    name: String,
+    #[serde(rename = "type")] btype: BreadcrumbType,
+    #[serde(skip_serializing_if = "Map::is_empty")] meta_data: Map<String, Value>,
+}
+
+impl Breadcrumb {
+    /// Creates a new breadcrumb with the current time as timestamp.
+    pub fn new(name: &str, btype: BreadcrumbType) -> Breadcrumb {
+        Breadcrumb::with_timestamp(name, btype, &timestamp::now())
+    }
+
+    fn with_timestamp(name: &str, btype: BreadcrumbType, timestamp: &str) -> Breadcrumb {
+        Breadcrumb {
+            timestamp: timestamp.to_owned(),
+            name: name.to_owned(),
+            btype,
+            meta_data: Map::new(),
+        }
+    }
+
+    /// Adds additional information to the breadcrumb. The value needs to be
+    /// convertible to json.
+    pub fn set_metadata<T: Serialize + ?Sized>(
+        &mut self,
+        key: &str,
+        value: &T,
+    ) -> Result<(), Error> {
+        let value = serde_json::to_value(value).map_err(|_| Error::JsonConversionFailed)?;
+        self.meta_data.insert(key.to_owned(), value);
+        Ok(())
+    }
+}
+
+/// A bounded ring buffer of breadcrumbs. If the buffer is full, the oldest
+/// breadcrumb is removed.
+#[derive(Debug)]
+pub struct Breadcrumbs {
+    max: usize,
+    items: Mutex<VecDeque<Breadcrumb>>,
+}
+
+impl Breadcrumbs {
+    pub fn new(max: usize) -> Breadcrumbs {
+        Breadcrumbs {
+            max,
+            items: Mutex::new(VecDeque::with_capacity(max)),
+        }
+    }
+
+    pub fn push(&self, breadcrumb: Breadcrumb) {
+        if self.max == 0 {
+            return;
+        }
+
+        let mut items = lock(&self.items);
+        while items.len() >= self.max {
+            items.pop_front();
+        }
+        items.push_back(breadcrumb);
+    }
+
+    /// Sets the maximum number of stored breadcrumbs and removes the oldest
+    /// breadcrumbs that exceed the new maximum.
+    pub fn set_max(&mut self, max: usize) {
+        self.max = max;
+
+        let mut items = lock(&self.items);
+        while items.len() > max {
+            items.pop_front();
+        }
+    }
+
+    pub fn clear(&self) {
+        lock(&self.items).clear();
+    }
+
+    /// Returns a copy of the stored breadcrumbs, the oldest breadcrumb first.
+    pub fn to_vec(&self) -> Vec<Breadcrumb> {
+        lock(&self.items).iter().cloned().collect()
+    }
+}
+
+/// Locks the buffer, a poisoned lock is ignored, because the buffer is always
+/// in a consistent state.
+fn lock(items: &Mutex<VecDeque<Breadcrumb>>) -> MutexGuard<'_, VecDeque<Breadcrumb>> {
+    items.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Breadcrumb, BreadcrumbType, Breadcrumbs};
+    use serde_test::{assert_ser_tokens, Token};
+
+    #[test]
+    fn test_breadcrumb_to_json() {
+        let breadcrumb = Breadcrumb::with_timestamp(
+            "GET /index",
+            BreadcrumbType::Request,
+            "2018-03-01T12:30:00.000Z",
+        );
+
+        assert_ser_tokens(
+            &breadcrumb,
+            &[
+                Token::Struct {
+                    name: "Breadcrumb",
+                    len: 3,
+                },
+                Token::Str("timestamp"),
+                Token::Str("2018-03-01T12:30:00.000Z"),
+                Token::Str("name"),
+                Token::Str("GET /index"),
+                Token::Str("type"),
+                Token::UnitVariant {
+                    name: "BreadcrumbType",
+                    variant: "request",
+                },
+                Token::StructEnd,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_breadcrumb_with_metadata_to_json() {
+        let mut breadcrumb = Breadcrumb::with_timestamp(
+            "Start",
+            BreadcrumbType::Process,
+            "2018-03-01T12:30:00.000Z",
+        );
+        breadcrumb.set_metadata("pid", &42).unwrap();
+
+        assert_ser_tokens(
+            &breadcrumb,
+            &[
+                Token::Struct {
+                    name: "Breadcrumb",
+                    len: 4,
+                },
+                Token::Str("timestamp"),
+                Token::Str("2018-03-01T12:30:00.000Z"),
+                Token::Str("name"),
+                Token::Str("Start"),
+                Token::Str("type"),
+                Token::UnitVariant {
+                    name: "BreadcrumbType",
+                    variant: "process",
+                },
+                Token::Str("metaData"),
+                Token::Map { len: Some(1) },
+                Token::Str("pid"),
+                Token::U64(42),
+                Token::MapEnd,
+                Token::StructEnd,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_breadcrumbs_drop_oldest() {
+        let breadcrumbs = Breadcrumbs::new(2);
+        breadcrumbs.push(Breadcrumb::new("1", BreadcrumbType::Manual));
+        breadcrumbs.push(Breadcrumb::new("2", BreadcrumbType::Manual));
+        breadcrumbs.push(Breadcrumb::new("3", BreadcrumbType::Manual));
+
+        let names: Vec<_> = breadcrumbs.to_vec().into_iter().map(|b| b.name).collect();
+        assert_eq!(names, vec!["2", "3"]);
+    }
+
+    #[test]
+    fn test_breadcrumbs_set_max() {
+        let mut breadcrumbs = Breadcrumbs::new(3);
+        breadcrumbs.push(Breadcrumb::new("1", BreadcrumbType::Manual));
+        breadcrumbs.push(Breadcrumb::new("2", BreadcrumbType::Manual));
+        breadcrumbs.push(Breadcrumb::new("3", BreadcrumbType::Manual));
+        breadcrumbs.set_max(1);
+
+        let names: Vec<_> = breadcrumbs.to_vec().into_iter().map(|b| b.name).collect();
+        assert_eq!(names, vec!["3"]);
+
+        breadcrumbs.set_max(0);
+        breadcrumbs.push(Breadcrumb::new("4", BreadcrumbType::Manual));
+        assert!(breadcrumbs.to_vec().is_empty());
+    }
+}
@@ -1,42 +1,73 @@
-use super::{appinfo, deviceinfo, event, exception, notification, stacktrace};
+use super::{appinfo, breadcrumbs, delivery, deviceinfo, endpoints, event, exception,
+            frame_filter, grouping, metadata, method_names, outbox, path_normalizer, redaction,
+            report, retry, session, snippets, stacktrace, threads, transport, user};
 
 use std::fmt;
+use std::borrow::Cow;
 use std::error::Error as StdError;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-use serde_json;
+use serde::{Serialize, Serializer};
+use serde::ser::SerializeStruct;
 
-use hyper::Client;
-use hyper::header::ContentType;
-
-const NOTIFY_URL: &'static str = "http://notify.bugsnag.com";
-
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Error {
     /// The conversion to json failed.
     JsonConversionFailed,
-    /// While transferring the json to Bugsnag, a problem occurred.
-    /// This error does not reflect if Bugsnag rejected the json.
+    /// While transferring the json to Bugsnag, a problem occurred, e.g. a network error.
+    /// If Bugsnag rejected the json, one of the following errors is returned.
     JsonTransferFailed,
+    /// The notification was dropped, because the asynchronous delivery queue is full.
+    DeliveryQueueFull,
+    /// Bugsnag rejected the json as invalid (status 400).
+    BadRequest,
+    /// Bugsnag rejected the api key (status 401 or 403).
+    Unauthorized,
+    /// Bugsnag rejected the json, because it is too large (status 413).
+    PayloadTooLarge,
+    /// Bugsnag rejected the json, because too many notifications were send (status 429).
+    RateLimited,
+    /// Bugsnag failed to process the json (status 5xx).
+    ServerError(u16),
+    /// Bugsnag responded with an unexpected status.
+    UnexpectedStatus(u16),
+    /// Reading or writing the outbox failed.
+    OutboxFailed,
+    /// The TLS connector of the HTTP client could not be created, so no notification can
+    /// be send. Contains the error of the TLS library.
+    TlsInitFailed(String),
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.description())
-    }
-}
-
-impl StdError for Error {
-    fn description(&self) -> &'static str {
         match *self {
-            Error::JsonConversionFailed => "conversion to json failed",
+            Error::JsonConversionFailed => write!(f, "conversion to json failed"),
             Error::JsonTransferFailed => {
-                "while transferring the json to Bugsnag, a problem occurred"
+                write!(f, "while transferring the json to Bugsnag, a problem occurred")
+            }
+            Error::DeliveryQueueFull => write!(f, "the delivery queue is full"),
+            Error::BadRequest => write!(f, "Bugsnag rejected the json as invalid"),
+            Error::Unauthorized => write!(f, "Bugsnag rejected the api key"),
+            Error::PayloadTooLarge => write!(f, "Bugsnag rejected the json as too large"),
+            Error::RateLimited => write!(f, "Bugsnag rejected the json, because of rate limiting"),
+            Error::ServerError(status) => {
+                write!(f, "Bugsnag failed to process the json (status {})", status)
+            }
+            Error::UnexpectedStatus(status) => {
+                write!(f, "Bugsnag responded with the unexpected status {}", status)
+            }
+            Error::OutboxFailed => write!(f, "reading or writing the outbox failed"),
+            Error::TlsInitFailed(ref error) => {
+                write!(f, "the TLS connector could not be created: {}", error)
             }
         }
     }
 }
 
-#[derive(Debug, Serialize)]
+impl StdError for Error {}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum Severity {
     Error,
@@ -44,41 +75,143 @@ pub enum Severity {
     Info,
 }
 
+/// The reason for the severity of an event, which also decides if the event is shown as
+/// handled or unhandled by Bugsnag.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SeverityReason {
+    /// The application reported an error it handled, with the default severity.
+    HandledException,
+    /// The application set the severity explicitly.
+    UserSpecifiedSeverity,
+    /// The error was reported by a panic handler.
+    UnhandledPanic,
+    /// The error was reported by a logger, with the given log level.
+    Log(String),
+}
+
+impl SeverityReason {
+    /// Returns if the error was not handled by the application.
+    pub fn is_unhandled(&self) -> bool {
+        *self == SeverityReason::UnhandledPanic
+    }
+
+    fn reason_type(&self) -> &'static str {
+        match *self {
+            SeverityReason::HandledException => "handledException",
+            SeverityReason::UserSpecifiedSeverity => "userSpecifiedSeverity",
+            SeverityReason::UnhandledPanic => "unhandledPanic",
+            SeverityReason::Log(_) => "log",
+        }
+    }
+}
+
+impl Serialize for SeverityReason {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Attributes<'a> {
+            level: &'a str,
+        }
+
+        let level = match *self {
+            SeverityReason::Log(ref level) => Some(level),
+            _ => None,
+        };
+        let len = if level.is_some() { 2 } else { 1 };
+
+        let mut state = serializer.serialize_struct("SeverityReason", len)?;
+        state.serialize_field("type", self.reason_type())?;
+        if let Some(level) = level {
+            state.serialize_field("attributes", &Attributes { level })?;
+        }
+        state.end()
+    }
+}
+
+/// The outcome of sending a notification.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NotifyOutcome {
+    /// The notification was send, queued for the asynchronous delivery or stored in the
+    /// outbox.
+    Sent,
+    /// A `before_notify` callback discarded the notification.
+    Discarded,
+    /// The notification was not send, because the release stage of the application is not
+    /// one of the notify release stages.
+    ReleaseStageIgnored,
+}
+
+/// A callback that is called before a notification is send, see `Bugsnag::add_before_notify`.
+type BeforeNotify = Box<dyn Fn(&mut report::Report) -> bool + Send + Sync>;
+
 pub struct Bugsnag {
     api_key: String,
     device_info: deviceinfo::DeviceInfo,
     app_info: Option<appinfo::AppInfo>,
     project_source_dir: String,
+    metadata: metadata::MetaData,
+    user: Option<user::User>,
+    breadcrumbs: breadcrumbs::Breadcrumbs,
+    delivery_queue: Option<delivery::DeliveryQueue>,
+    endpoints: endpoints::Endpoints,
+    transport: Arc<dyn transport::Transport>,
+    retry_policy: retry::RetryPolicy,
+    outbox: Option<Arc<outbox::Outbox>>,
+    outbox_sweeper: Option<delivery::PeriodicTask>,
+    sessions: Arc<session::SessionTracker>,
+    session_flusher: Mutex<Option<delivery::PeriodicTask>>,
+    capture_threads: bool,
+    trim_rules: stacktrace::TrimRules,
+    frame_filter: frame_filter::ProjectFrameFilter,
+    path_normalizer: path_normalizer::PathNormalizer,
+    code_snippets: Option<snippets::CodeSnippets>,
+    symbolication: stacktrace::Symbolication,
+    method_names: method_names::MethodNames,
+    grouping_strategy: Option<Box<dyn grouping::GroupingStrategy>>,
+    error_classifier: Option<Box<dyn exception::ErrorClassifier>>,
+    before_notify: Vec<BeforeNotify>,
+    redacted_keys: redaction::RedactedKeys,
+    notify_release_stages: Option<Vec<String>>,
 }
 
 /// Builder for creating the notification that will be send to Bugsnag.
 /// If the object is dropped, the notification is send to Bugsnag.
 pub struct NotifyBuilder<'a, 'bugsnag> {
     bugsnag: &'bugsnag Bugsnag,
-    error_class: &'a str,
-    message: &'a str,
+    /// The error class and message of the error, followed by the ones of its causes.
+    errors: Vec<(Cow<'a, str>, Cow<'a, str>)>,
     send_executed: bool,
+    /// The result of the first call of `send`.
+    send_result: Option<Result<NotifyOutcome, Error>>,
+    synchronous: bool,
     methods_to_ignore: Option<&'a [&'a str]>,
     context: Option<&'a str>,
     severity: Option<Severity>,
+    severity_reason: Option<SeverityReason>,
     grouping_hash: Option<&'a str>,
+    metadata: metadata::MetaData,
+    metadata_failed: bool,
+    user: Option<user::User>,
 }
 
 impl<'a, 'bugsnag> NotifyBuilder<'a, 'bugsnag> {
     fn new(
         bugsnag: &'bugsnag Bugsnag,
-        error_class: &'a str,
-        message: &'a str,
+        errors: Vec<(Cow<'a, str>, Cow<'a, str>)>,
     ) -> NotifyBuilder<'a, 'bugsnag> {
         NotifyBuilder {
             bugsnag,
-            error_class,
-            message,
+            errors,
             send_executed: false,
+            send_result: None,
+            synchronous: false,
             methods_to_ignore: None,
             context: None,
             severity: None,
+            severity_reason: None,
             grouping_hash: None,
+            metadata: metadata::MetaData::new(),
+            metadata_failed: false,
+            user: None,
         }
     }
 
@@ -99,54 +232,155 @@ impl<'a, 'bugsnag> NotifyBuilder<'a, 'bugsnag> {
         self
     }
 
-    /// Sets the severity of the error.
+    /// Sets the severity of the error. The severity reason is `UserSpecifiedSeverity`,
+    /// if no other reason is set.
     pub fn severity(mut self, val: Severity) -> Self {
         self.severity = Some(val);
+        self.severity_reason
+            .get_or_insert(SeverityReason::UserSpecifiedSeverity);
+        self
+    }
+
+    /// Sets the reason for the severity of the error. Without a reason, the error is
+    /// reported as a handled exception.
+    pub fn severity_reason(mut self, reason: SeverityReason) -> Self {
+        self.severity_reason = Some(reason);
         self
     }
 
-    /// Sets the grouping hash for the Bugsnag web interface.
+    /// Sets the grouping hash for the Bugsnag web interface. The hash takes precedence over
+    /// the hash of the grouping strategy.
     pub fn grouping_hash(mut self, val: &'a str) -> Self {
         self.grouping_hash = Some(val);
         self
     }
 
+    /// Adds custom diagnostic data to the given tab of the notification. The value needs
+    /// to be convertible to json. The tab is merged with the tab set with
+    /// `Bugsnag::set_metadata`: nested objects are merged key by key, all other values set
+    /// with this function take precedence.
+    pub fn metadata<T: Serialize + ?Sized>(mut self, tab: &str, value: &T) -> Self {
+        if self.metadata.set_tab(tab, value).is_err() {
+            self.metadata_failed = true;
+        }
+        self
+    }
+
+    /// Sets the user that was affected by the error. This user takes precedence over the
+    /// user of the current thread and the user set with `Bugsnag::set_user`.
+    pub fn user(mut self, val: user::User) -> Self {
+        self.user = Some(val);
+        self
+    }
+
+    /// Bypasses the asynchronous delivery queue, because the process is about to die.
+    /// If an outbox is set, the notification is stored before it is send.
+    pub(crate) fn synchronous(mut self) -> Self {
+        self.synchronous = true;
+        self
+    }
+
     /// Call this function to explicitly send the notification to Bugsnag.
     /// This function will be called implicit if this object is dropped, but the notification will
     /// not be send twice.
-    pub fn send(&mut self) -> Result<(), Error> {
+    ///
+    /// If asynchronous delivery is enabled, the notification is only queued for delivery.
+    /// If the symbolication is deferred and an outbox is set, the notification is only
+    /// stored in the outbox. Further calls return the result of the first call.
+    pub fn send(&mut self) -> Result<NotifyOutcome, Error> {
         if self.send_executed {
-            return Ok(());
+            return self.send_result.clone().unwrap_or(Ok(NotifyOutcome::Sent));
         }
 
         self.send_executed = true;
 
-        let json = self.prepare_json()?;
-        self.bugsnag.send(&json)
+        if !self.bugsnag.notifies_release_stage() {
+            self.send_result = Some(Ok(NotifyOutcome::ReleaseStageIgnored));
+            return Ok(NotifyOutcome::ReleaseStageIgnored);
+        }
+
+        let result = self.create_report()
+            .and_then(|report| self.bugsnag.process_report(report, self.synchronous));
+        self.send_result = Some(result.clone());
+        result
+    }
+
+    /// Creates the report of the notification, instead of sending it. The report can be
+    /// send later, e.g. from another thread, with `Bugsnag::send_report`. The `before_notify`
+    /// callbacks are called when the report is send.
+    pub fn into_report(mut self) -> Result<report::Report, Error> {
+        self.send_executed = true;
+        self.create_report()
     }
 
     /// Prepares the json as string
+    #[cfg(test)]
     fn prepare_json(&self) -> Result<String, Error> {
-        let stacktrace = self.bugsnag.create_stacktrace(self.methods_to_ignore);
-        let exceptions = vec![
-            exception::Exception::new(self.error_class, self.message, &stacktrace),
-        ];
-        let events = vec![
-            event::Event::new(
-                &exceptions,
-                self.severity.as_ref(),
-                self.context,
-                self.grouping_hash,
-                &self.bugsnag.device_info,
-                &self.bugsnag.app_info,
-            ),
-        ];
-        let notification = notification::Notification::new(&self.bugsnag.api_key, &events);
-
-        match serde_json::to_string(&notification) {
-            Ok(json) => Ok(json),
-            Err(_) => Err(Error::JsonConversionFailed),
+        self.create_report()?.to_json()
+    }
+
+    /// Collects everything that is send to Bugsnag.
+    fn create_report(&self) -> Result<report::Report, Error> {
+        if self.metadata_failed {
+            return Err(Error::JsonConversionFailed);
         }
+
+        let stacktrace = self.bugsnag.create_stacktrace(self.methods_to_ignore);
+        let grouping_hash = match (self.grouping_hash, &self.bugsnag.grouping_strategy) {
+            (Some(grouping_hash), _) => Some(grouping_hash.to_owned()),
+            (None, Some(strategy)) => self.errors.first().and_then(|(error_class, message)| {
+                strategy.grouping_hash(&grouping::GroupedError::new(
+                    error_class,
+                    message,
+                    &stacktrace,
+                ))
+            }),
+            (None, None) => None,
+        };
+        let threads = if self.bugsnag.capture_threads {
+            threads::capture(&stacktrace, |addresses| {
+                self.bugsnag
+                    .build_stacktrace(self.methods_to_ignore, |in_project| {
+                        stacktrace::resolve_addresses(
+                            addresses,
+                            &in_project,
+                            self.bugsnag.symbolication,
+                        )
+                    })
+            })
+        } else {
+            Vec::new()
+        };
+        let severity_reason = self.severity_reason
+            .clone()
+            .unwrap_or(SeverityReason::HandledException);
+
+        let errors = self.errors
+            .iter()
+            .map(|(error_class, message)| (error_class.to_string(), message.to_string()))
+            .collect();
+        let mut report = report::Report::new(
+            &self.bugsnag.api_key,
+            errors,
+            stacktrace,
+            self.bugsnag.device_info.clone(),
+            self.bugsnag.app_info.clone(),
+        );
+        report.set_severity(self.severity.clone());
+        report.set_severity_reason(severity_reason);
+        report.set_context(self.context.map(|context| context.to_owned()));
+        report.set_grouping_hash(grouping_hash);
+        report.replace_metadata(self.bugsnag.metadata.merge(&self.metadata));
+        report.set_user(
+            self.user
+                .clone()
+                .or_else(user::thread_user)
+                .or_else(|| self.bugsnag.user.clone()),
+        );
+        report.set_breadcrumbs(self.bugsnag.breadcrumbs.to_vec());
+        report.set_threads(threads);
+        report.set_redacted_keys(self.bugsnag.redacted_keys.clone());
+        Ok(report)
     }
 }
 
@@ -158,12 +392,41 @@ impl<'a, 'bugsnag> Drop for NotifyBuilder<'a, 'bugsnag> {
 
 impl Bugsnag {
     /// Creates a new instance of the Bugsnag api
+    ///
+    /// The notifications are send to the public Bugsnag endpoints, if the endpoints
+    /// are not configured by the environment variables `BUGSNAG_NOTIFY_ENDPOINT` and
+    /// `BUGSNAG_SESSIONS_ENDPOINT`. Use `set_endpoints` or the `BugsnagBuilder` to
+    /// configure the endpoints explicitly.
     pub fn new(api_key: &str, project_source_dir: &str) -> Bugsnag {
         Bugsnag {
             api_key: api_key.to_owned(),
             device_info: deviceinfo::DeviceInfo::generate(),
             app_info: None,
             project_source_dir: project_source_dir.to_owned(),
+            metadata: metadata::MetaData::new(),
+            user: None,
+            breadcrumbs: breadcrumbs::Breadcrumbs::new(breadcrumbs::DEFAULT_MAX_BREADCRUMBS),
+            delivery_queue: None,
+            endpoints: endpoints::Endpoints::from_env(),
+            transport: transport::default_transport(),
+            retry_policy: retry::RetryPolicy::default(),
+            outbox: None,
+            outbox_sweeper: None,
+            sessions: Arc::new(session::SessionTracker::new()),
+            session_flusher: Mutex::new(None),
+            capture_threads: false,
+            trim_rules: stacktrace::TrimRules::default(),
+            frame_filter: frame_filter::ProjectFrameFilter::new()
+                .source_root(project_source_dir),
+            path_normalizer: path_normalizer::PathNormalizer::new().prefix(project_source_dir),
+            code_snippets: None,
+            symbolication: stacktrace::Symbolication::Immediate,
+            method_names: method_names::MethodNames::new(),
+            grouping_strategy: None,
+            error_classifier: None,
+            before_notify: Vec::new(),
+            redacted_keys: redaction::RedactedKeys::new(),
+            notify_release_stages: None,
         }
     }
 
@@ -174,38 +437,205 @@ impl Bugsnag {
         error_class: &'a str,
         message: &'a str,
     ) -> NotifyBuilder<'a, 'bugsnag> {
-        NotifyBuilder::new(&self, error_class, message)
+        NotifyBuilder::new(self, vec![(error_class.into(), message.into())])
     }
 
-    fn create_stacktrace(&self, methods_to_ignore: Option<&[&str]>) -> Vec<stacktrace::Frame> {
-        if let Some(ignore) = methods_to_ignore {
-            let in_project_check = |file: &str, method: &str| {
-                file.starts_with(self.project_source_dir.as_str())
-                    && ignore
-                        .iter()
-                        .find(|check| !method.contains(*check))
-                        .is_some()
-            };
-
-            stacktrace::create_stacktrace(&in_project_check)
-        } else {
-            let in_project_check =
-                |file: &str, _: &str| file.starts_with(self.project_source_dir.as_str());
+    /// Notifies the Bugsnag web-interface about an error and all errors that caused it
+    /// (see `std::error::Error::source`). Every error of the chain is reported as separate
+    /// exception with the type of the error as error class and the `Display` output as message.
+    /// The function returns a builder to provide more information about the error.
+    ///
+    /// The type of the causes, and of the error if it is a trait object, is only known for
+    /// the errors of the standard library, e.g. `std::io::Error`. The error classes of the
+    /// other causes are returned by the error classifier, see `set_error_classifier`. All
+    /// remaining errors have the error class `Error`.
+    pub fn notify_error<'a, 'bugsnag, E: StdError + ?Sized>(
+        &'bugsnag self,
+        error: &E,
+    ) -> NotifyBuilder<'a, 'bugsnag> {
+        let errors = exception::error_chain(error, self.error_classifier.as_deref())
+            .into_iter()
+            .map(|(error_class, message)| (error_class.into(), message.into()))
+            .collect();
+
+        NotifyBuilder::new(self, errors)
+    }
+
+    /// Sends a report that was created with `NotifyBuilder::into_report`.
+    ///
+    /// If asynchronous delivery is enabled, the report is only queued for delivery.
+    pub fn send_report(&self, report: report::Report) -> Result<NotifyOutcome, Error> {
+        if !self.notifies_release_stage() {
+            return Ok(NotifyOutcome::ReleaseStageIgnored);
+        }
+
+        self.process_report(report, false)
+    }
 
-            stacktrace::create_stacktrace(&in_project_check)
+    /// Returns if the current release stage is one of the notify release stages.
+    fn notifies_release_stage(&self) -> bool {
+        let stages = match self.notify_release_stages {
+            Some(ref stages) => stages,
+            None => return true,
+        };
+        let stage = self.app_info
+            .as_ref()
+            .and_then(|app_info| app_info.release_stage());
+
+        stage.is_some_and(|stage| stages.iter().any(|s| s == stage))
+    }
+
+    /// Calls the `before_notify` callbacks with the report and delivers it, unless a
+    /// callback discarded it. Only delivered reports count as events of the session of the
+    /// calling thread.
+    fn process_report(
+        &self,
+        mut report: report::Report,
+        synchronous: bool,
+    ) -> Result<NotifyOutcome, Error> {
+        if !self.before_notify.iter().all(|callback| callback(&mut report)) {
+            return Ok(NotifyOutcome::Discarded);
         }
+
+        let session = self.sessions
+            .track_event(report.severity_reason().is_unhandled());
+        report.set_session(session);
+        self.deliver(report.to_json()?, synchronous)?;
+        Ok(NotifyOutcome::Sent)
     }
 
-    /// Send a json string to the Bugsnag endpoint
-    fn send(&self, json: &str) -> Result<(), Error> {
-        match Client::new()
-            .post(NOTIFY_URL)
-            .header(ContentType::json())
-            .body(json)
-            .send()
+    /// Delivers the json of a notification. If the symbolication is deferred and an outbox
+    /// is set, the json is only stored in the outbox. Synchronous deliveries bypass the
+    /// asynchronous delivery queue.
+    fn deliver(&self, json: String, synchronous: bool) -> Result<(), Error> {
+        if let (stacktrace::Symbolication::Deferred, Some(outbox)) =
+            (self.symbolication, &self.outbox)
         {
-            Ok(_) => Ok(()),
-            Err(_) => Err(Error::JsonTransferFailed),
+            // the stacktrace is symbolicated offline, before the notification is send
+            return outbox
+                .store_unresolved(&json)
+                .map(|_| ())
+                .map_err(|_| Error::OutboxFailed);
+        }
+
+        let delivery = self.delivery();
+        match self.delivery_queue {
+            _ if synchronous => delivery.send_persistent(&json),
+            Some(ref queue) => queue.push(Box::new(move || {
+                let _ = delivery.send(&json);
+            })),
+            None => delivery.send(&json),
+        }
+    }
+
+    /// Returns the current configuration for delivering a json to Bugsnag.
+    fn delivery(&self) -> delivery::Delivery {
+        delivery::Delivery {
+            transport: self.transport.clone(),
+            retry_policy: self.retry_policy.clone(),
+            url: self.endpoints.notify().to_owned(),
+            api_key: self.api_key.clone(),
+            payload_version: event::PAYLOAD_VERSION.to_string(),
+            outbox: self.outbox.clone(),
+        }
+    }
+
+    /// Returns the current configuration for delivering the session counts to Bugsnag.
+    fn session_delivery(&self) -> delivery::Delivery {
+        delivery::Delivery {
+            transport: self.transport.clone(),
+            retry_policy: self.retry_policy.clone(),
+            url: self.endpoints.sessions().to_owned(),
+            api_key: self.api_key.clone(),
+            payload_version: session::PAYLOAD_VERSION.to_owned(),
+            // the outbox only holds notifications
+            outbox: None,
+        }
+    }
+
+    /// Starts the background thread that sends the session counts, if it is not running.
+    fn start_session_flusher(&self) {
+        let mut flusher = self.session_flusher.lock().unwrap_or_else(|e| e.into_inner());
+        if flusher.is_some() {
+            return;
+        }
+
+        let sessions = self.sessions.clone();
+        let delivery = self.session_delivery();
+        let app_info = self.app_info.clone();
+        let device_info = self.device_info.clone();
+        *flusher = Some(delivery::PeriodicTask::new(
+            "bugsnag-sessions",
+            session::FLUSH_INTERVAL,
+            move || {
+                let _ = sessions.send(&delivery, &app_info, &device_info);
+            },
+        ));
+    }
+
+    fn create_stacktrace(&self, methods_to_ignore: Option<&[&str]>) -> Vec<stacktrace::Frame> {
+        self.build_stacktrace(methods_to_ignore, |in_project| {
+            stacktrace::create_stacktrace(&in_project, self.symbolication)
+        })
+    }
+
+    /// Creates the frames with `create`, which gets the check if a frame belongs to the
+    /// project, and cleans them up: the method names are cleaned, the frames of the
+    /// notifier and of the runtime are trimmed and the paths are normalized.
+    fn build_stacktrace<C>(
+        &self,
+        methods_to_ignore: Option<&[&str]>,
+        create: C,
+    ) -> Vec<stacktrace::Frame>
+    where
+        C: FnOnce(&dyn Fn(&str, &str) -> bool) -> Vec<stacktrace::Frame>,
+    {
+        let ignored = methods_to_ignore.unwrap_or(&[]);
+        let in_project_check = |file: &str, method: &str| {
+            self.frame_filter.is_in_project(file, method)
+                && !ignored.iter().any(|ignored| method.contains(ignored))
+        };
+
+        let mut frames = create(&in_project_check);
+        for frame in &mut frames {
+            let method = self.method_names.clean(frame.method());
+            frame.set_method(method);
+        }
+        let mut frames = self.trim_rules.trim(frames);
+
+        // the snippets are read before the paths are changed
+        if let Some(ref code_snippets) = self.code_snippets {
+            code_snippets.apply(&mut frames);
+        }
+        for frame in &mut frames {
+            let file = self.path_normalizer.normalize(frame.file());
+            frame.set_file(file);
+        }
+        frames
+    }
+
+    /// Enables the asynchronous delivery of notifications. Notifications are queued and
+    /// send from a background thread, so sending a notification does not block the caller.
+    /// The queue holds up to `capacity` notifications, the `policy` decides what happens
+    /// if the queue is full.
+    ///
+    /// Call `flush` before the application exits, to wait for pending notifications.
+    pub fn enable_async_delivery(&mut self, capacity: usize, policy: delivery::OverflowPolicy) {
+        self.delivery_queue = Some(delivery::DeliveryQueue::new(capacity, policy));
+    }
+
+    /// Disables the asynchronous delivery. Notifications that are still queued will be
+    /// send in the background.
+    pub fn disable_async_delivery(&mut self) {
+        self.delivery_queue = None;
+    }
+
+    /// Waits until all queued notifications are send or the timeout elapsed.
+    /// Returns `true` if no notification is pending anymore.
+    pub fn flush(&self, timeout: Duration) -> bool {
+        match self.delivery_queue {
+            Some(ref queue) => queue.flush(timeout),
+            None => true,
         }
     }
 
@@ -236,6 +666,283 @@ impl Bugsnag {
         self.app_info = None;
     }
 
+    /// Sets custom diagnostic data in the given tab. These information will be send
+    /// to Bugsnag with every notification. The value needs to be convertible to json.
+    pub fn set_metadata<T: Serialize + ?Sized>(
+        &mut self,
+        tab: &str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.metadata.set_tab(tab, value)
+    }
+
+    /// Removes the custom diagnostic data of the given tab.
+    pub fn reset_metadata(&mut self, tab: &str) {
+        self.metadata.remove_tab(tab);
+    }
+
+    /// Sets the user that will be send to Bugsnag when notify is called. The user of the
+    /// current thread (see `user::set_thread_user`) takes precedence over this user.
+    pub fn set_user(&mut self, user: user::User) {
+        self.user = Some(user);
+    }
+
+    pub fn reset_user(&mut self) {
+        self.user = None;
+    }
+
+    /// Records a breadcrumb. The recorded breadcrumbs will be send to Bugsnag
+    /// when notify is called.
+    pub fn leave_breadcrumb(&self, breadcrumb: breadcrumbs::Breadcrumb) {
+        self.breadcrumbs.push(breadcrumb);
+    }
+
+    /// Removes all recorded breadcrumbs.
+    pub fn clear_breadcrumbs(&self) {
+        self.breadcrumbs.clear();
+    }
+
+    /// Sets the maximum number of recorded breadcrumbs. If more breadcrumbs are
+    /// recorded, the oldest breadcrumbs are removed. The default is 25.
+    pub fn set_max_breadcrumbs(&mut self, max: usize) {
+        self.breadcrumbs.set_max(max);
+    }
+
+    /// Sets the urls the notifications and sessions are send to.
+    pub fn set_endpoints(&mut self, endpoints: endpoints::Endpoints) {
+        self.endpoints = endpoints;
+    }
+
+    pub fn get_endpoints(&self) -> &endpoints::Endpoints {
+        &self.endpoints
+    }
+
+    /// Sets the transport that sends the notifications to Bugsnag. By default, the
+    /// HTTP client that is enabled by the cargo features is used.
+    pub fn set_transport<T: transport::Transport + 'static>(&mut self, transport: T) {
+        self.transport = Arc::new(transport);
+    }
+
+    /// Sets the policy for retrying deliveries that failed because of transient problems.
+    /// By default, deliveries are not retried. Retries block the calling thread, unless
+    /// the asynchronous delivery is enabled.
+    pub fn set_retry_policy(&mut self, retry_policy: retry::RetryPolicy) {
+        self.retry_policy = retry_policy;
+    }
+
+    /// Sets the outbox that stores notifications that could not be delivered, because of a
+    /// transient problem. Use `send_outbox` or `start_outbox_sweeper` to deliver the stored
+    /// notifications later.
+    pub fn set_outbox(&mut self, outbox: outbox::Outbox) {
+        self.outbox = Some(Arc::new(outbox));
+    }
+
+    pub fn reset_outbox(&mut self) {
+        self.outbox = None;
+        self.outbox_sweeper = None;
+    }
+
+    /// Sends the notifications that are stored in the outbox, e.g. at the startup of the
+    /// application. Returns the number of delivered notifications.
+    ///
+    /// If a notification can not be delivered because of a transient problem, the remaining
+    /// notifications are kept in the outbox and the error is returned.
+    pub fn send_outbox(&self) -> Result<usize, Error> {
+        self.delivery().send_outbox()
+    }
+
+    /// Starts a background thread that sends the notifications stored in the outbox
+    /// periodically. The thread uses the configuration at the time this function is called
+    /// and is stopped when this object is dropped or the outbox is reset.
+    pub fn start_outbox_sweeper(&mut self, interval: Duration) {
+        let delivery = self.delivery();
+        self.outbox_sweeper = Some(delivery::PeriodicTask::new(
+            "bugsnag-outbox",
+            interval,
+            move || {
+                let _ = delivery.send_outbox();
+            },
+        ));
+    }
+
+    /// Starts a new session for the calling thread, e.g. when a request is handled. Bugsnag
+    /// uses the sessions to calculate the stability of the application. The following
+    /// notifications of the calling thread belong to this session and count as its handled
+    /// or unhandled events. Every thread has its own session, so concurrent requests do not
+    /// replace the sessions of each other.
+    ///
+    /// The number of started sessions is send to the sessions endpoint every minute by a
+    /// background thread. The thread uses the configuration at the time the first session
+    /// is started, so the endpoints and the transport should be set before. Use
+    /// `flush_sessions` to send the session counts before the application exits.
+    ///
+    /// No session is started if the release stage is not notified.
+    pub fn start_session(&self) {
+        if !self.notifies_release_stage() {
+            return;
+        }
+
+        self.sessions.start();
+        self.start_session_flusher();
+    }
+
+    /// Pauses the current session of the calling thread. The following notifications of the
+    /// thread do not belong to a session, until the session is resumed or a new session is
+    /// started.
+    pub fn pause_session(&self) {
+        self.sessions.pause();
+    }
+
+    /// Resumes the paused session of the calling thread. If there is no session, a new
+    /// session is started.
+    pub fn resume_session(&self) {
+        if !self.notifies_release_stage() {
+            return;
+        }
+
+        self.sessions.resume();
+        self.start_session_flusher();
+    }
+
+    /// Sends the session counts, that were not send yet, to the sessions endpoint.
+    /// Nothing is send if the release stage is not notified.
+    pub fn flush_sessions(&self) -> Result<(), Error> {
+        if !self.notifies_release_stage() {
+            return Ok(());
+        }
+
+        self.sessions
+            .send(&self.session_delivery(), &self.app_info, &self.device_info)
+    }
+
+    /// Enables or disables capturing all threads of the process with every notification,
+    /// e.g. to see where the other threads are blocked in a deadlock.
+    ///
+    /// The threads can only be captured on Linux, on all other platforms only the reporting
+    /// thread is captured. The stacktraces of the other threads are recorded by a handler
+    /// of the signal `SIGRTMIN + 4` on every thread, which is best effort: threads that
+    /// block the signal or do not handle it in time are reported without a stacktrace, and
+    /// if the application handles this signal itself, no other stacktraces are captured.
+    /// Disabled by default.
+    pub fn set_capture_threads(&mut self, capture_threads: bool) {
+        self.capture_threads = capture_threads;
+    }
+
+    /// Sets the rules for removing the frames at the top and the bottom of the
+    /// stacktraces, that do not belong to the application. By default, the frames of the
+    /// notifier, of the panic machinery and of the runtime are removed.
+    pub fn set_trim_rules(&mut self, trim_rules: stacktrace::TrimRules) {
+        self.trim_rules = trim_rules;
+    }
+
+    /// Sets the filter that decides which frames belong to the project. This replaces
+    /// the default filter, which only uses the project source dir.
+    pub fn set_frame_filter(&mut self, frame_filter: frame_filter::ProjectFrameFilter) {
+        self.frame_filter = frame_filter;
+    }
+
+    /// Sets the normalizer that converts the absolute paths of the files in the stacktraces
+    /// into relative paths. This replaces the default normalizer, which removes the project
+    /// source dir, the cargo registry and the compiler sysroot from the paths.
+    pub fn set_path_normalizer(&mut self, path_normalizer: path_normalizer::PathNormalizer) {
+        self.path_normalizer = path_normalizer;
+    }
+
+    /// Sets the settings for the code snippets, that are included in the frames that belong
+    /// to the project. Snippets are disabled by default, because the source files are read
+    /// on the notifying thread. Use `None` to disable them again.
+    pub fn set_code_snippets(&mut self, code_snippets: Option<snippets::CodeSnippets>) {
+        self.code_snippets = code_snippets;
+    }
+
+    /// Sets when the addresses of the stacktraces are resolved. By default, they are resolved
+    /// immediately with the debug info of the running binary.
+    ///
+    /// For stripped binaries, the symbolication can be deferred. The stacktraces then only
+    /// contain the addresses, the load addresses and the build ids of the object files. If
+    /// an outbox is set, the notifications are only stored in the outbox, so that they can
+    /// be symbolicated with the separate debug file, e.g. by the `bugsnag-symbolicate`
+    /// tool, before they are delivered with `send_outbox`. Neither `send_outbox` nor the
+    /// outbox sweeper send a notification before it was symbolicated.
+    pub fn set_symbolication(&mut self, symbolication: stacktrace::Symbolication) {
+        self.symbolication = symbolication;
+    }
+
+    /// Sets how the method names of the frames are cleaned up. By default, the hashes are
+    /// removed from the names, so that the same error is grouped together across builds.
+    pub fn set_method_names(&mut self, method_names: method_names::MethodNames) {
+        self.method_names = method_names;
+    }
+
+    /// Sets the strategy that computes the grouping hash of the notifications, e.g. a
+    /// `FingerprintGrouping`. Without a strategy, Bugsnag groups the notifications.
+    pub fn set_grouping_strategy<T: grouping::GroupingStrategy + 'static>(&mut self, strategy: T) {
+        self.grouping_strategy = Some(Box::new(strategy));
+    }
+
+    pub fn reset_grouping_strategy(&mut self) {
+        self.grouping_strategy = None;
+    }
+
+    /// Sets the classifier that returns the error classes of the causes of the errors, that
+    /// are notified with `notify_error`. Without a classifier, only the errors of the
+    /// standard library have a specific error class.
+    pub fn set_error_classifier<T: exception::ErrorClassifier + 'static>(&mut self, classifier: T) {
+        self.error_classifier = Some(Box::new(classifier));
+    }
+
+    pub fn reset_error_classifier(&mut self) {
+        self.error_classifier = None;
+    }
+
+    /// Adds a callback that is called with every report before it is send. The callbacks
+    /// are called in the order they were added and can modify the report, e.g. to add
+    /// metadata or to rewrite the context. If a callback returns `false`, the report is
+    /// discarded, the following callbacks are not called and `NotifyBuilder::send` returns
+    /// `NotifyOutcome::Discarded`.
+    ///
+    /// ```
+    /// let mut api = bugsnag::Bugsnag::new("api-key", env!("CARGO_MANIFEST_DIR"));
+    /// api.add_before_notify(|report: &mut bugsnag::Report| {
+    ///     report.errors()[0].0 != "ConnectionReset"
+    /// });
+    /// api.add_before_notify(|report: &mut bugsnag::Report| {
+    ///     report.set_metadata("tenant", &"acme").is_ok()
+    /// });
+    /// ```
+    pub fn add_before_notify<F>(&mut self, callback: F)
+    where
+        F: Fn(&mut report::Report) -> bool + Send + Sync + 'static,
+    {
+        self.before_notify.push(Box::new(callback));
+    }
+
+    /// Removes all `before_notify` callbacks.
+    pub fn clear_before_notify(&mut self) {
+        self.before_notify.clear();
+    }
+
+    /// Sets the keys whose values are replaced with `[REDACTED]` in the notifications, e.g.
+    /// in the metadata and the breadcrumbs. By default, keys like `password`, `cookie` and
+    /// `token` are redacted. The keys match exactly, ignoring the case, use a regex of
+    /// `RedactedKeys::pattern` to redact all keys that contain a string.
+    pub fn set_redacted_keys(&mut self, redacted_keys: redaction::RedactedKeys) {
+        self.redacted_keys = redacted_keys;
+    }
+
+    /// Sets the release stages that are notified, e.g. `production` and `staging`. If the
+    /// release stage of the app info is not in the list, or no release stage is set, the
+    /// notifications are not send and `NotifyBuilder::send` returns
+    /// `NotifyOutcome::ReleaseStageIgnored`. No sessions are tracked or send for these
+    /// release stages either. By default, all release stages are notified.
+    pub fn set_notify_release_stages(&mut self, stages: &[&str]) {
+        self.notify_release_stages = Some(stages.iter().map(|stage| (*stage).to_owned()).collect());
+    }
+
+    pub fn reset_notify_release_stages(&mut self) {
+        self.notify_release_stages = None;
+    }
+
     pub fn get_project_source_dir(&self) -> &String {
         &self.project_source_dir
     }
@@ -243,7 +950,17 @@ impl Bugsnag {
 
 #[cfg(test)]
 mod tests {
-    use super::{Bugsnag, Severity};
+    use super::{Bugsnag, Error, NotifyOutcome, Severity, SeverityReason};
+    use super::super::report::Report;
+    use super::super::user::{self, User};
+    use super::super::breadcrumbs::{Breadcrumb, BreadcrumbType};
+    use super::super::transport::{RecordingTransport, Response};
+    use super::super::{CodeSnippets, Endpoints, FingerprintGrouping, MethodNames, Outbox,
+                       OverflowPolicy, RedactedKeys, RetryPolicy, Symbolication, TrimRules};
+    use std::time::Duration;
+    use serde_json::{self, json, Value};
+    use std::error::Error as StdError;
+    use std::fmt;
     use serde_test::{assert_ser_tokens, Token};
 
     #[test]
@@ -291,9 +1008,666 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_severity_reason_to_json() {
+        assert_ser_tokens(
+            &SeverityReason::UnhandledPanic,
+            &[
+                Token::Struct {
+                    name: "SeverityReason",
+                    len: 1,
+                },
+                Token::Str("type"),
+                Token::Str("unhandledPanic"),
+                Token::StructEnd,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_log_severity_reason_to_json() {
+        assert_ser_tokens(
+            &SeverityReason::Log("warn".to_owned()),
+            &[
+                Token::Struct {
+                    name: "SeverityReason",
+                    len: 2,
+                },
+                Token::Str("type"),
+                Token::Str("log"),
+                Token::Str("attributes"),
+                Token::Struct {
+                    name: "Attributes",
+                    len: 1,
+                },
+                Token::Str("level"),
+                Token::Str("warn"),
+                Token::StructEnd,
+                Token::StructEnd,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_notify_severity_reason() {
+        let api = Bugsnag::new("api-key", "my-dir");
+
+        let event = |notify: &mut super::NotifyBuilder| {
+            notify.send_executed = true;
+            let json: Value = serde_json::from_str(&notify.prepare_json().unwrap()).unwrap();
+            json["events"][0].clone()
+        };
+
+        let handled = event(&mut api.notify("Info", "Test"));
+        assert_eq!(handled["unhandled"], false);
+        assert_eq!(handled["severityReason"]["type"], "handledException");
+
+        let specified = event(&mut api.notify("Info", "Test").severity(Severity::Info));
+        assert_eq!(specified["unhandled"], false);
+        assert_eq!(specified["severityReason"]["type"], "userSpecifiedSeverity");
+
+        let panic = event(&mut api.notify("Panic", "Test")
+            .severity_reason(SeverityReason::UnhandledPanic)
+            .severity(Severity::Error));
+        assert_eq!(panic["unhandled"], true);
+        assert_eq!(panic["severity"], "error");
+        assert_eq!(panic["severityReason"]["type"], "unhandledPanic");
+    }
+
+    #[test]
+    fn test_send_report_from_other_thread() {
+        use std::sync::Arc;
+        use std::thread;
+
+        let transport = RecordingTransport::new(200);
+        let mut api = Bugsnag::new("api-key", "my-dir");
+        api.set_transport(transport.clone());
+        let api = Arc::new(api);
+
+        let report = api.notify("Info", "Test")
+            .severity(Severity::Info)
+            .context("test/context")
+            .into_report()
+            .unwrap();
+        assert!(transport.requests().is_empty());
+        assert_eq!(report.errors(), &[("Info".to_owned(), "Test".to_owned())]);
+        assert_eq!(report.context(), Some("test/context"));
+
+        let sender = api.clone();
+        thread::spawn(move || sender.send_report(report))
+            .join()
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(transport.requests().len(), 1);
+        let json: Value = serde_json::from_str(&transport.requests()[0].body).unwrap();
+        assert_eq!(json["events"][0]["context"], "test/context");
+        assert_eq!(json["events"][0]["severity"], "info");
+    }
+
+    #[test]
+    fn test_notify_grouping_hash() {
+        let mut api = Bugsnag::new("api-key", "my-dir");
+
+        let event = |notify: &mut super::NotifyBuilder| {
+            notify.send_executed = true;
+            let json: Value = serde_json::from_str(&notify.prepare_json().unwrap()).unwrap();
+            json["events"][0].clone()
+        };
+
+        assert_eq!(event(&mut api.notify("Info", "Test"))["groupingHash"], Value::Null);
+        assert_eq!(
+            event(&mut api.notify("Info", "Test").grouping_hash("my-hash"))["groupingHash"],
+            "my-hash"
+        );
+
+        api.set_grouping_strategy(FingerprintGrouping::new());
+        let first = event(&mut api.notify("NotFound", "user 42"));
+        let second = event(&mut api.notify("NotFound", "user 43"));
+        assert!(first["groupingHash"].is_string());
+        assert_eq!(first["groupingHash"], second["groupingHash"]);
+        assert_eq!(
+            event(&mut api.notify("Info", "Test").grouping_hash("my-hash"))["groupingHash"],
+            "my-hash"
+        );
+    }
+
     #[test]
     fn test_get_project_dir() {
         let api = Bugsnag::new("api-key", "my-dir");
         assert_eq!(api.get_project_source_dir(), "my-dir");
     }
+
+    #[test]
+    fn test_notify_metadata_overrides_global_metadata() {
+        let global: Value = serde_json::from_str(r#"{"global": 1, "shared": 1}"#).unwrap();
+        let local: Value = serde_json::from_str(r#"{"shared": 2}"#).unwrap();
+        let mut api = Bugsnag::new("api-key", "my-dir");
+        api.set_metadata("tab", &global).unwrap();
+
+        let mut notify = api.notify("Info", "Test").metadata("tab", &local);
+        notify.send_executed = true;
+
+        let json: Value = serde_json::from_str(&notify.prepare_json().unwrap()).unwrap();
+        assert_eq!(
+            json["events"][0]["metaData"]["tab"].to_string(),
+            r#"{"global":1,"shared":2}"#
+        );
+    }
+
+    #[test]
+    fn test_notify_user_precedence() {
+        let mut api = Bugsnag::new("api-key", "my-dir");
+        api.set_user(User::new(Some("global"), None, None));
+
+        let user_id = |notify: &mut super::NotifyBuilder| {
+            notify.send_executed = true;
+            let json: Value = serde_json::from_str(&notify.prepare_json().unwrap()).unwrap();
+            json["events"][0]["user"]["id"].as_str().unwrap().to_owned()
+        };
+
+        assert_eq!(user_id(&mut api.notify("Info", "Test")), "global");
+
+        user::set_thread_user(User::new(Some("thread"), None, None));
+        assert_eq!(user_id(&mut api.notify("Info", "Test")), "thread");
+        assert_eq!(
+            user_id(&mut api.notify("Info", "Test").user(User::new(Some("local"), None, None))),
+            "local"
+        );
+        user::reset_thread_user();
+    }
+
+    #[test]
+    fn test_notify_with_breadcrumbs() {
+        let api = Bugsnag::new("api-key", "my-dir");
+        api.leave_breadcrumb(Breadcrumb::new("first", BreadcrumbType::Manual));
+        api.leave_breadcrumb(Breadcrumb::new("second", BreadcrumbType::State));
+
+        let mut notify = api.notify("Info", "Test");
+        notify.send_executed = true;
+
+        let json: Value = serde_json::from_str(&notify.prepare_json().unwrap()).unwrap();
+        let breadcrumbs = json["events"][0]["breadcrumbs"].as_array().unwrap();
+        assert_eq!(breadcrumbs.len(), 2);
+        assert_eq!(breadcrumbs[0]["name"], "first");
+        assert_eq!(breadcrumbs[1]["type"], "state");
+    }
+
+    #[derive(Debug)]
+    struct ConnectError(::std::num::ParseIntError);
+
+    impl fmt::Display for ConnectError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "could not connect")
+        }
+    }
+
+    impl StdError for ConnectError {
+        fn source(&self) -> Option<&(dyn StdError + 'static)> {
+            Some(&self.0)
+        }
+    }
+
+    #[test]
+    fn test_notify_error_reports_source_chain() {
+        let api = Bugsnag::new("api-key", "my-dir");
+        let error = ConnectError("port".parse::<u16>().unwrap_err());
+
+        let mut notify = api.notify_error(&error);
+        notify.send_executed = true;
+
+        let json: Value = serde_json::from_str(&notify.prepare_json().unwrap()).unwrap();
+        let exceptions = json["events"][0]["exceptions"].as_array().unwrap();
+        assert_eq!(exceptions.len(), 2);
+        assert_eq!(exceptions[0]["errorClass"], "ConnectError");
+        assert_eq!(exceptions[0]["message"], "could not connect");
+        assert_eq!(exceptions[1]["errorClass"], "std::num::ParseIntError");
+        assert_eq!(exceptions[1]["message"], "invalid digit found in string");
+        assert_eq!(exceptions[1]["stacktrace"].as_array().unwrap().len(), 0);
+    }
+
+    #[test]
+    fn test_send_uses_transport() {
+        let transport = RecordingTransport::new(200);
+        let mut api = Bugsnag::new("api-key", "my-dir");
+        api.set_endpoints(Endpoints::new("http://localhost:9000", "http://localhost:9001"));
+        api.set_transport(transport.clone());
+
+        api.notify("Info", "Test").send().unwrap();
+
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].url, "http://localhost:9000");
+
+        let header = |name: &str| {
+            requests[0]
+                .headers
+                .iter()
+                .find(|header| header.0 == name)
+                .map(|header| header.1.clone())
+        };
+        assert_eq!(header("Content-Type").unwrap(), "application/json");
+        assert_eq!(header("Bugsnag-Api-Key").unwrap(), "api-key");
+        assert_eq!(header("Bugsnag-Payload-Version").unwrap(), "4");
+        assert!(header("Bugsnag-Sent-At").unwrap().ends_with('Z'));
+
+        let json: Value = serde_json::from_str(&requests[0].body).unwrap();
+        assert_eq!(json["events"][0]["exceptions"][0]["message"], "Test");
+    }
+
+    #[test]
+    fn test_async_delivery_uses_transport() {
+        let transport = RecordingTransport::new(200);
+        let mut api = Bugsnag::new("api-key", "my-dir");
+        api.set_transport(transport.clone());
+        api.enable_async_delivery(10, OverflowPolicy::DropNewest);
+
+        api.notify("Info", "First");
+        api.notify("Info", "Second");
+
+        assert!(api.flush(Duration::from_secs(5)));
+        assert_eq!(transport.requests().len(), 2);
+    }
+
+    #[test]
+    fn test_no_sessions_for_ignored_release_stages() {
+        let transport = RecordingTransport::new(200);
+        let mut api = Bugsnag::new("api-key", "my-dir");
+        api.set_endpoints(Endpoints::new("http://localhost:9000", "http://localhost:9001"));
+        api.set_transport(transport.clone());
+        api.set_notify_release_stages(&["production"]);
+        api.set_app_info(Some("1.0.0"), Some("development"), None);
+
+        api.start_session();
+        api.notify("Info", "Test").send().unwrap();
+        api.resume_session();
+        api.flush_sessions().unwrap();
+
+        assert!(transport.requests().is_empty());
+        assert!(api.session_flusher.lock().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_send_reports_rejection() {
+        let transport = RecordingTransport::new(401);
+        let mut api = Bugsnag::new("api-key", "my-dir");
+        api.set_transport(transport.clone());
+
+        assert_eq!(
+            api.notify("Info", "Test").send().unwrap_err(),
+            Error::Unauthorized
+        );
+    }
+
+    #[test]
+    fn test_send_retries_transient_failures() {
+        let transport = RecordingTransport::with_responses(
+            202,
+            vec![
+                Err(Error::JsonTransferFailed),
+                Ok(Response::new(503)),
+                Ok(Response::new(429).with_retry_after(Some(Duration::from_millis(1)))),
+            ],
+        );
+        let mut api = Bugsnag::new("api-key", "my-dir");
+        api.set_transport(transport.clone());
+        api.set_retry_policy(RetryPolicy::new(4, Duration::from_millis(1)));
+
+        assert_eq!(api.notify("Info", "Test").send(), Ok(NotifyOutcome::Sent));
+        assert_eq!(transport.requests().len(), 4);
+    }
+
+    #[test]
+    fn test_send_gives_up_on_long_retry_after() {
+        let transport = RecordingTransport::with_responses(
+            202,
+            vec![Ok(Response::new(429).with_retry_after(Some(Duration::from_secs(3600))))],
+        );
+        let mut api = Bugsnag::new("api-key", "my-dir");
+        api.set_transport(transport.clone());
+        api.set_retry_policy(
+            RetryPolicy::new(4, Duration::from_millis(1)).max_delay(Duration::from_secs(1)),
+        );
+
+        assert_eq!(api.notify("Info", "Test").send(), Err(Error::RateLimited));
+        assert_eq!(transport.requests().len(), 1);
+    }
+
+    #[test]
+    fn test_send_gives_up_after_max_attempts() {
+        let transport = RecordingTransport::new(500);
+        let mut api = Bugsnag::new("api-key", "my-dir");
+        api.set_transport(transport.clone());
+        api.set_retry_policy(RetryPolicy::new(3, Duration::from_millis(1)));
+
+        assert_eq!(
+            api.notify("Info", "Test").send(),
+            Err(Error::ServerError(500))
+        );
+        assert_eq!(transport.requests().len(), 3);
+    }
+
+    #[test]
+    fn test_send_does_not_retry_rejected_json() {
+        let transport = RecordingTransport::new(400);
+        let mut api = Bugsnag::new("api-key", "my-dir");
+        api.set_transport(transport.clone());
+        api.set_retry_policy(RetryPolicy::new(3, Duration::from_millis(1)));
+
+        assert_eq!(api.notify("Info", "Test").send(), Err(Error::BadRequest));
+        assert_eq!(transport.requests().len(), 1);
+    }
+
+    #[test]
+    fn test_outbox_stores_undelivered_notifications() {
+        let dir = ::std::env::temp_dir()
+            .join(format!("bugsnag-impl-outbox-{}", ::std::process::id()));
+        let _ = ::std::fs::remove_dir_all(&dir);
+
+        let offline = RecordingTransport::new(503);
+        let mut api = Bugsnag::new("api-key", "my-dir");
+        api.set_transport(offline.clone());
+        api.set_outbox(Outbox::new(&dir));
+
+        assert_eq!(
+            api.notify("Info", "Test").send(),
+            Err(Error::ServerError(503))
+        );
+        assert_eq!(api.notify("Info", "Rejected").send(), Err(Error::ServerError(503)));
+
+        let online = RecordingTransport::new(202);
+        api.set_transport(online.clone());
+
+        assert_eq!(api.send_outbox(), Ok(2));
+        assert_eq!(online.requests().len(), 2);
+        assert_eq!(online.requests()[0].body, offline.requests()[0].body);
+        assert_eq!(api.send_outbox(), Ok(0));
+        let _ = ::std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_deferred_symbolication_holds_notifications() {
+        let dir = ::std::env::temp_dir()
+            .join(format!("bugsnag-impl-deferred-{}", ::std::process::id()));
+        let _ = ::std::fs::remove_dir_all(&dir);
+
+        let transport = RecordingTransport::new(200);
+        let mut api = Bugsnag::new("api-key", "my-dir");
+        api.set_transport(transport.clone());
+        api.set_symbolication(Symbolication::Deferred);
+        api.set_outbox(Outbox::new(&dir));
+
+        assert_eq!(api.notify("Info", "Test").send(), Ok(NotifyOutcome::Sent));
+        assert!(transport.requests().is_empty());
+        // the notification is not sent before it is symbolicated
+        assert_eq!(api.send_outbox(), Ok(0));
+        assert!(transport.requests().is_empty());
+
+        // the symbolication makes the notification pending
+        for entry in ::std::fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            ::std::fs::rename(&path, path.with_extension("json")).unwrap();
+        }
+        assert_eq!(api.send_outbox(), Ok(1));
+        let json: Value = serde_json::from_str(&transport.requests()[0].body).unwrap();
+        let frames = json["events"][0]["exceptions"][0]["stacktrace"]
+            .as_array()
+            .unwrap();
+        assert!(!frames.is_empty());
+        assert!(frames.iter().all(|frame| frame["method"] == "unknown"));
+        assert!(frames.iter().all(|frame| frame["frameAddress"].is_string()));
+        let _ = ::std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_events_belong_to_session() {
+        let transport = RecordingTransport::new(200);
+        let mut api = Bugsnag::new("api-key", "my-dir");
+        api.set_endpoints(Endpoints::new("http://localhost:9000", "http://localhost:9001"));
+        api.set_transport(transport.clone());
+
+        api.notify("Info", "Without session").send().unwrap();
+        api.start_session();
+        api.notify("Info", "Handled").send().unwrap();
+        api.notify("Panic", "Unhandled")
+            .severity_reason(SeverityReason::UnhandledPanic)
+            .send().unwrap();
+        api.pause_session();
+        api.notify("Info", "Paused").send().unwrap();
+
+        let events: Vec<Value> = transport
+            .requests()
+            .iter()
+            .map(|request| serde_json::from_str::<Value>(&request.body).unwrap())
+            .map(|json| json["events"][0].clone())
+            .collect();
+        assert_eq!(events[0].get("session"), None);
+        assert_eq!(events[1]["session"]["events"]["handled"], 1);
+        assert_eq!(events[2]["session"]["events"]["handled"], 1);
+        assert_eq!(events[2]["session"]["events"]["unhandled"], 1);
+        assert_eq!(events[1]["session"]["id"], events[2]["session"]["id"]);
+        assert_eq!(events[3].get("session"), None);
+
+        api.flush_sessions().unwrap();
+
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 5);
+        assert_eq!(requests[4].url, "http://localhost:9001");
+        let json: Value = serde_json::from_str(&requests[4].body).unwrap();
+        assert_eq!(json["sessionCounts"][0]["sessionsStarted"], 1);
+    }
+
+    #[test]
+    fn test_before_notify_modifies_reports() {
+        let transport = RecordingTransport::new(200);
+        let mut api = Bugsnag::new("api-key", "my-dir");
+        api.set_endpoints(Endpoints::new("http://localhost:9000", "http://localhost:9001"));
+        api.set_transport(transport.clone());
+        api.add_before_notify(|report: &mut Report| {
+            report.set_context(report.context().map(|context| context.replace("42", ":id")));
+            report.set_metadata("tenant", &"acme").is_ok()
+        });
+        api.add_before_notify(|report: &mut Report| {
+            // the callbacks are called in the order they were added
+            assert!(report.metadata("tenant").is_some());
+            report.errors_mut()[0].1 = "rewritten".to_owned();
+            report.set_severity(Some(Severity::Warning));
+            report.set_user(Some(User::new(Some("1"), None, None)));
+            true
+        });
+
+        assert_eq!(
+            api.notify("Info", "Test").context("users/42").send(),
+            Ok(NotifyOutcome::Sent)
+        );
+
+        let json: Value = serde_json::from_str(&transport.requests()[0].body).unwrap();
+        let event = &json["events"][0];
+        assert_eq!(event["context"], "users/:id");
+        assert_eq!(event["metaData"]["tenant"]["value"], "acme");
+        assert_eq!(event["exceptions"][0]["message"], "rewritten");
+        assert_eq!(event["severity"], "warning");
+        assert_eq!(event["user"]["id"], "1");
+    }
+
+    #[test]
+    fn test_before_notify_discards_reports() {
+        let transport = RecordingTransport::new(200);
+        let mut api = Bugsnag::new("api-key", "my-dir");
+        api.set_endpoints(Endpoints::new("http://localhost:9000", "http://localhost:9001"));
+        api.set_transport(transport.clone());
+        api.add_before_notify(|report: &mut Report| report.errors()[0].0 != "Benign");
+        api.add_before_notify(|_: &mut Report| panic!("not called for discarded reports"));
+
+        api.start_session();
+        {
+            let mut notify = api.notify("Benign", "Test");
+            assert_eq!(notify.send(), Ok(NotifyOutcome::Discarded));
+            assert_eq!(notify.send(), Ok(NotifyOutcome::Discarded));
+        }
+        assert!(transport.requests().is_empty());
+
+        api.clear_before_notify();
+        assert_eq!(api.notify("Benign", "Test").send(), Ok(NotifyOutcome::Sent));
+
+        // discarded reports do not count as events of the session
+        let json: Value = serde_json::from_str(&transport.requests()[0].body).unwrap();
+        assert_eq!(json["events"][0]["session"]["events"]["handled"], 1);
+    }
+
+    #[test]
+    fn test_notify_redacts_keys() {
+        let mut api = Bugsnag::new("api-key", "my-dir");
+        api.set_metadata("request", &json!({"headers": {"Authorization": "Bearer abc"}}))
+            .unwrap();
+        let mut breadcrumb = Breadcrumb::new("Login", BreadcrumbType::Manual);
+        breadcrumb.set_metadata("password", &"hunter2").unwrap();
+        api.leave_breadcrumb(breadcrumb);
+
+        let json = {
+            let mut notify = api.notify("Info", "Test").metadata("login", &json!({"pin": 1234}));
+            notify.send_executed = true;
+            notify.prepare_json().unwrap()
+        };
+        let json: Value = serde_json::from_str(&json).unwrap();
+        let event = &json["events"][0];
+        assert_eq!(event["metaData"]["request"]["headers"]["Authorization"], "[REDACTED]");
+        assert_eq!(event["metaData"]["login"]["pin"], 1234);
+        assert_eq!(event["breadcrumbs"][0]["metaData"]["password"], "[REDACTED]");
+
+        api.set_redacted_keys(RedactedKeys::none().key("pin"));
+        let mut notify = api.notify("Info", "Test").metadata("login", &json!({"pin": 1234}));
+        notify.send_executed = true;
+        let json: Value = serde_json::from_str(&notify.prepare_json().unwrap()).unwrap();
+        let event = &json["events"][0];
+        assert_eq!(event["metaData"]["request"]["headers"]["Authorization"], "Bearer abc");
+        assert_eq!(event["metaData"]["login"]["pin"], "[REDACTED]");
+    }
+
+    #[test]
+    fn test_notify_release_stages() {
+        let transport = RecordingTransport::new(200);
+        let mut api = Bugsnag::new("api-key", "my-dir");
+        api.set_endpoints(Endpoints::new("http://localhost:9000", "http://localhost:9001"));
+        api.set_transport(transport.clone());
+        api.set_notify_release_stages(&["production", "staging"]);
+
+        // without a release stage, nothing is notified
+        assert_eq!(
+            api.notify("Info", "Test").send(),
+            Ok(NotifyOutcome::ReleaseStageIgnored)
+        );
+        api.set_app_info(Some("1.0.0"), Some("development"), None);
+        assert_eq!(
+            api.notify("Info", "Test").send(),
+            Ok(NotifyOutcome::ReleaseStageIgnored)
+        );
+        let report = api.notify("Info", "Test").into_report().unwrap();
+        assert_eq!(api.send_report(report), Ok(NotifyOutcome::ReleaseStageIgnored));
+        assert!(transport.requests().is_empty());
+
+        api.set_app_info(Some("1.0.0"), Some("staging"), None);
+        assert_eq!(api.notify("Info", "Test").send(), Ok(NotifyOutcome::Sent));
+        api.set_app_info(Some("1.0.0"), Some("development"), None);
+        api.reset_notify_release_stages();
+        assert_eq!(api.notify("Info", "Test").send(), Ok(NotifyOutcome::Sent));
+        assert_eq!(transport.requests().len(), 2);
+    }
+
+    #[test]
+    fn test_notify_with_threads() {
+        let mut api = Bugsnag::new("api-key", "my-dir");
+
+        let threads = |notify: &mut super::NotifyBuilder| {
+            notify.send_executed = true;
+            let json: Value = serde_json::from_str(&notify.prepare_json().unwrap()).unwrap();
+            json["events"][0].get("threads").cloned()
+        };
+
+        assert_eq!(threads(&mut api.notify("Info", "Test")), None);
+
+        api.set_capture_threads(true);
+        let threads = threads(&mut api.notify("Info", "Test")).unwrap();
+        let reporting: Vec<_> = threads
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|thread| thread["errorReportingThread"] == true)
+            .collect();
+        assert_eq!(reporting.len(), 1);
+        assert!(!reporting[0]["stacktrace"].as_array().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_stacktrace_paths_are_relative() {
+        let mut api = Bugsnag::new("api-key", env!("CARGO_MANIFEST_DIR"));
+        api.set_trim_rules(TrimRules::none());
+
+        let frames = api.create_stacktrace(None);
+        assert!(frames.iter().all(|frame| !frame.file().starts_with("/rustc/")));
+        let frame = frames
+            .iter()
+            .find(|frame| frame.file().ends_with("bugsnag_impl.rs"))
+            .unwrap();
+        assert_eq!(frame.file(), "src/bugsnag_impl.rs");
+    }
+
+    #[test]
+    fn test_stacktrace_method_names() {
+        let mut api = Bugsnag::new("api-key", env!("CARGO_MANIFEST_DIR"));
+        api.set_trim_rules(TrimRules::none());
+        let in_closure = |api: &Bugsnag| -> Vec<String> {
+            api.create_stacktrace(None)
+                .iter()
+                .map(|frame| frame.method().to_owned())
+                .filter(|method| method.contains("test_stacktrace_method_names"))
+                .collect()
+        };
+
+        // the last frame is the closure of the test harness
+        assert_eq!(
+            in_closure(&api)[..2],
+            [
+                "bugsnag::bugsnag_impl::tests::test_stacktrace_method_names::{{closure}}",
+                "bugsnag::bugsnag_impl::tests::test_stacktrace_method_names",
+            ]
+        );
+
+        api.set_method_names(MethodNames::new().simplify_closures(true));
+        assert_eq!(
+            in_closure(&api)[..2],
+            [
+                "bugsnag::bugsnag_impl::tests::test_stacktrace_method_names",
+                "bugsnag::bugsnag_impl::tests::test_stacktrace_method_names",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stacktrace_code_snippets() {
+        let mut api = Bugsnag::new("api-key", env!("CARGO_MANIFEST_DIR"));
+        api.set_trim_rules(TrimRules::none());
+
+        let snippets = |api: &Bugsnag| -> Vec<_> {
+            api.create_stacktrace(None)
+                .into_iter()
+                .filter(|frame| frame.file() == "src/bugsnag_impl.rs" && frame.in_project())
+                .filter_map(|frame| frame.code().cloned())
+                .collect()
+        };
+
+        assert!(snippets(&api).is_empty());
+
+        api.set_code_snippets(Some(CodeSnippets::new()));
+        let code = snippets(&api);
+        assert!(code.iter().all(|code| code.len() == 7));
+        assert!(
+            code.iter()
+                .flat_map(|code| code.values())
+                .any(|line| line.contains("api.create_stacktrace(None)"))
+        );
+
+        api.set_code_snippets(None);
+        assert!(snippets(&api).is_empty());
+    }
 }
@@ -0,0 +1,170 @@
+//! Module for configuring a `Bugsnag` instance before it is used.
+
+use super::{Bugsnag, CodeSnippets, Endpoints, ErrorClassifier, GroupingStrategy, MethodNames,
+            Outbox, PathNormalizer, ProjectFrameFilter, RedactedKeys, Report, RetryPolicy,
+            Symbolication, Transport, TrimRules};
+
+/// Builder for creating a `Bugsnag` instance.
+///
+/// # Example
+///
+/// ```
+/// let api = bugsnag::BugsnagBuilder::new("api-key", env!("CARGO_MANIFEST_DIR"))
+///     .notify_endpoint("https://bugsnag.example.com:49000")
+///     .sessions_endpoint("https://bugsnag.example.com:49001")
+///     .build();
+/// ```
+pub struct BugsnagBuilder {
+    bugsnag: Bugsnag,
+}
+
+impl BugsnagBuilder {
+    /// Creates a new builder, see `Bugsnag::new` for the defaults.
+    pub fn new(api_key: &str, project_source_dir: &str) -> BugsnagBuilder {
+        BugsnagBuilder {
+            bugsnag: Bugsnag::new(api_key, project_source_dir),
+        }
+    }
+
+    /// Sets the url the notifications are send to.
+    pub fn notify_endpoint(mut self, url: &str) -> Self {
+        let endpoints = Endpoints::new(url, self.bugsnag.get_endpoints().sessions());
+        self.bugsnag.set_endpoints(endpoints);
+        self
+    }
+
+    /// Sets the url the sessions are send to.
+    pub fn sessions_endpoint(mut self, url: &str) -> Self {
+        let endpoints = Endpoints::new(self.bugsnag.get_endpoints().notify(), url);
+        self.bugsnag.set_endpoints(endpoints);
+        self
+    }
+
+    /// Sets the urls the notifications and sessions are send to.
+    pub fn endpoints(mut self, endpoints: Endpoints) -> Self {
+        self.bugsnag.set_endpoints(endpoints);
+        self
+    }
+
+    /// Sets the transport that sends the notifications to Bugsnag.
+    pub fn transport<T: Transport + 'static>(mut self, transport: T) -> Self {
+        self.bugsnag.set_transport(transport);
+        self
+    }
+
+    /// Sets the policy for retrying deliveries that failed because of transient problems.
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.bugsnag.set_retry_policy(retry_policy);
+        self
+    }
+
+    /// Sets the outbox that stores notifications that could not be delivered.
+    pub fn outbox(mut self, outbox: Outbox) -> Self {
+        self.bugsnag.set_outbox(outbox);
+        self
+    }
+
+    /// Enables or disables capturing all threads of the process with every notification, see
+    /// `Bugsnag::set_capture_threads`.
+    pub fn capture_threads(mut self, capture_threads: bool) -> Self {
+        self.bugsnag.set_capture_threads(capture_threads);
+        self
+    }
+
+    /// Sets the rules for removing the frames that do not belong to the application.
+    pub fn trim_rules(mut self, trim_rules: TrimRules) -> Self {
+        self.bugsnag.set_trim_rules(trim_rules);
+        self
+    }
+
+    /// Sets the filter that decides which frames belong to the project.
+    pub fn frame_filter(mut self, frame_filter: ProjectFrameFilter) -> Self {
+        self.bugsnag.set_frame_filter(frame_filter);
+        self
+    }
+
+    /// Sets the normalizer that converts the paths in the stacktraces into relative paths.
+    pub fn path_normalizer(mut self, path_normalizer: PathNormalizer) -> Self {
+        self.bugsnag.set_path_normalizer(path_normalizer);
+        self
+    }
+
+    /// Sets the settings for the code snippets, `None` disables them. Disabled by default.
+    pub fn code_snippets(mut self, code_snippets: Option<CodeSnippets>) -> Self {
+        self.bugsnag.set_code_snippets(code_snippets);
+        self
+    }
+
+    /// Sets when the addresses of the stacktraces are resolved, see
+    /// `Bugsnag::set_symbolication`.
+    pub fn symbolication(mut self, symbolication: Symbolication) -> Self {
+        self.bugsnag.set_symbolication(symbolication);
+        self
+    }
+
+    /// Sets how the method names of the frames are cleaned up.
+    pub fn method_names(mut self, method_names: MethodNames) -> Self {
+        self.bugsnag.set_method_names(method_names);
+        self
+    }
+
+    /// Sets the strategy that computes the grouping hash of the notifications.
+    pub fn grouping_strategy<T: GroupingStrategy + 'static>(mut self, strategy: T) -> Self {
+        self.bugsnag.set_grouping_strategy(strategy);
+        self
+    }
+
+    /// Sets the classifier that returns the error classes of the causes of the errors, see
+    /// `Bugsnag::set_error_classifier`.
+    pub fn error_classifier<T: ErrorClassifier + 'static>(mut self, classifier: T) -> Self {
+        self.bugsnag.set_error_classifier(classifier);
+        self
+    }
+
+    /// Adds a callback that is called with every report before it is send, see
+    /// `Bugsnag::add_before_notify`.
+    pub fn before_notify<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&mut Report) -> bool + Send + Sync + 'static,
+    {
+        self.bugsnag.add_before_notify(callback);
+        self
+    }
+
+    /// Sets the keys whose values are replaced with `[REDACTED]` in the notifications.
+    pub fn redacted_keys(mut self, redacted_keys: RedactedKeys) -> Self {
+        self.bugsnag.set_redacted_keys(redacted_keys);
+        self
+    }
+
+    /// Sets the release stages that are notified, see `Bugsnag::set_notify_release_stages`.
+    pub fn notify_release_stages(mut self, stages: &[&str]) -> Self {
+        self.bugsnag.set_notify_release_stages(stages);
+        self
+    }
+
+    /// Creates the configured `Bugsnag` instance.
+    pub fn build(self) -> Bugsnag {
+        self.bugsnag
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BugsnagBuilder;
+    use super::super::Endpoints;
+
+    #[test]
+    fn test_builder_endpoints() {
+        let api = BugsnagBuilder::new("api-key", "my-dir")
+            .notify_endpoint("http://localhost:9000")
+            .sessions_endpoint("http://localhost:9001")
+            .build();
+
+        assert_eq!(
+            api.get_endpoints(),
+            &Endpoints::new("http://localhost:9000", "http://localhost:9001")
+        );
+        assert_eq!(api.get_project_source_dir(), "my-dir");
+    }
+}
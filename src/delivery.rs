@@ -0,0 +1,568 @@
+//! Module for delivering notifications to Bugsnag, either on the calling thread or
+//! asynchronously on a background thread.
+
+use std::collections::VecDeque;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::{timestamp, Error};
+use super::outbox::Outbox;
+use super::retry::RetryPolicy;
+use super::transport::Transport;
+
+/// Everything that is required to deliver a json to Bugsnag.
+#[derive(Clone)]
+pub struct Delivery {
+    pub transport: Arc<dyn Transport>,
+    pub retry_policy: RetryPolicy,
+    pub url: String,
+    pub api_key: String,
+    pub payload_version: String,
+    pub outbox: Option<Arc<Outbox>>,
+}
+
+impl Delivery {
+    /// Sends the json to Bugsnag. If the delivery failed because of a transient problem,
+    /// the json is stored in the outbox.
+    pub fn send(&self, json: &str) -> Result<(), Error> {
+        let result = self.send_with_retries(json);
+
+        if let (Err(err), Some(outbox)) = (&result, &self.outbox) {
+            if is_undelivered(err) {
+                let _ = outbox.store(json);
+            }
+        }
+
+        result
+    }
+
+    /// Stores the json in the outbox before it is send to Bugsnag, so that the json is
+    /// not lost if the process dies while sending. The json is claimed while it is sent, so
+    /// that it is not sent by `send_outbox` too, and removed from the outbox if it does not
+    /// need to be send again.
+    pub fn send_persistent(&self, json: &str) -> Result<(), Error> {
+        let stored = match self.outbox {
+            Some(ref outbox) => outbox.store_claimed(json).ok().map(|path| (outbox, path)),
+            None => None,
+        };
+
+        let result = self.send_with_retries(json);
+
+        if let Some((outbox, claimed)) = stored {
+            let _ = match result {
+                Err(ref err) if is_undelivered(err) => outbox.release(&claimed),
+                _ => outbox.remove(&claimed),
+            };
+        }
+
+        result
+    }
+
+    /// Sends the notifications that are stored in the outbox, the oldest notification
+    /// first. Delivered notifications and notifications that were rejected by Bugsnag are
+    /// removed from the outbox. Returns the number of delivered notifications.
+    ///
+    /// If a delivery fails because of a transient problem, the remaining notifications are
+    /// kept in the outbox and the error is returned.
+    pub fn send_outbox(&self) -> Result<usize, Error> {
+        let outbox = match self.outbox {
+            Some(ref outbox) => outbox,
+            None => return Ok(0),
+        };
+
+        let mut delivered = 0;
+        for path in outbox.pending().map_err(|_| Error::OutboxFailed)? {
+            let claimed = match outbox.claim(&path) {
+                Ok(Some(claimed)) => claimed,
+                // the notification is sent or was delivered in the meantime
+                Ok(None) => continue,
+                Err(_) => return Err(Error::OutboxFailed),
+            };
+            let json = match fs::read_to_string(&claimed) {
+                Ok(json) => json,
+                Err(_) => {
+                    let _ = outbox.release(&claimed);
+                    return Err(Error::OutboxFailed);
+                }
+            };
+
+            match self.send_with_retries(&json) {
+                Err(ref err) if is_undelivered(err) => {
+                    outbox.release(&claimed).map_err(|_| Error::OutboxFailed)?;
+                    return Err(err.clone());
+                }
+                result => {
+                    if result.is_ok() {
+                        delivered += 1;
+                    }
+                    outbox.remove(&claimed).map_err(|_| Error::OutboxFailed)?;
+                }
+            }
+        }
+
+        Ok(delivered)
+    }
+
+    /// Sends the json to Bugsnag, retrying transient failures as defined by the
+    /// retry policy.
+    fn send_with_retries(&self, json: &str) -> Result<(), Error> {
+        let mut attempt = 1;
+
+        loop {
+            let sent_at = timestamp::now();
+            let headers = [
+                ("Content-Type", "application/json"),
+                ("Bugsnag-Api-Key", self.api_key.as_str()),
+                ("Bugsnag-Payload-Version", self.payload_version.as_str()),
+                ("Bugsnag-Sent-At", sent_at.as_str()),
+            ];
+
+            let (result, retry_after) = match self.transport.send(&self.url, &headers, json) {
+                Ok(response) => (check_status(response.status()), response.retry_after()),
+                Err(err) => (Err(err), None),
+            };
+
+            let delay = match result {
+                Err(ref err)
+                    if attempt < self.retry_policy.max_attempts()
+                        && RetryPolicy::is_retryable(err) =>
+                {
+                    self.retry_policy.delay(attempt, retry_after)
+                }
+                _ => None,
+            };
+
+            match delay {
+                Some(delay) => {
+                    thread::sleep(delay);
+                    attempt += 1;
+                }
+                None => return result,
+            }
+        }
+    }
+}
+
+/// Returns if the json was not delivered because of a problem that might be solved later,
+/// so that the json is kept in the outbox. Besides the retryable errors, this is the case if
+/// the HTTP client could not be created, e.g. because of a missing TLS library.
+fn is_undelivered(error: &Error) -> bool {
+    RetryPolicy::is_retryable(error) || matches!(*error, Error::TlsInitFailed(_))
+}
+
+/// Converts the status of a response into an error, if Bugsnag did not accept the json.
+pub fn check_status(status: u16) -> Result<(), Error> {
+    match status {
+        200..=299 => Ok(()),
+        400 => Err(Error::BadRequest),
+        401 | 403 => Err(Error::Unauthorized),
+        413 => Err(Error::PayloadTooLarge),
+        429 => Err(Error::RateLimited),
+        500..=599 => Err(Error::ServerError(status)),
+        _ => Err(Error::UnexpectedStatus(status)),
+    }
+}
+
+/// A background thread that executes a task after every interval, e.g. sending the
+/// notifications stored in the outbox. The thread is stopped when this object is dropped.
+pub struct PeriodicTask {
+    stop: Arc<(Mutex<bool>, Condvar)>,
+}
+
+impl PeriodicTask {
+    pub fn new<F>(name: &str, interval: Duration, task: F) -> PeriodicTask
+    where
+        F: Fn() + Send + 'static,
+    {
+        let stop = Arc::new((Mutex::new(false), Condvar::new()));
+        let thread_stop = stop.clone();
+
+        thread::Builder::new()
+            .name(name.to_owned())
+            .spawn(move || {
+                let (ref stopped, ref condvar) = *thread_stop;
+
+                loop {
+                    let guard = stopped.lock().unwrap_or_else(|e| e.into_inner());
+                    let (guard, _) = condvar
+                        .wait_timeout_while(guard, interval, |stopped| !*stopped)
+                        .unwrap_or_else(|e| e.into_inner());
+                    if *guard {
+                        return;
+                    }
+                    drop(guard);
+
+                    let _ = panic::catch_unwind(AssertUnwindSafe(&task));
+                }
+            })
+            .expect("failed to spawn a bugsnag thread");
+
+        PeriodicTask { stop }
+    }
+}
+
+impl Drop for PeriodicTask {
+    fn drop(&mut self) {
+        *self.stop.0.lock().unwrap_or_else(|e| e.into_inner()) = true;
+        self.stop.1.notify_all();
+    }
+}
+
+/// A delivery that is executed on the background thread.
+pub type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// Defines what happens if a notification is send while the delivery queue is full.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OverflowPolicy {
+    /// The oldest pending notification is dropped to make room for the new one.
+    DropOldest,
+    /// The new notification is dropped.
+    DropNewest,
+    /// The caller is blocked until there is room in the queue.
+    Block,
+}
+
+struct State {
+    jobs: VecDeque<Job>,
+    in_flight: bool,
+    shutdown: bool,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    /// Notified whenever the state changed.
+    changed: Condvar,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// A bounded queue with a worker thread that executes the queued deliveries.
+pub struct DeliveryQueue {
+    shared: Arc<Shared>,
+    capacity: usize,
+    policy: OverflowPolicy,
+}
+
+impl DeliveryQueue {
+    /// Creates a new queue that holds up to `capacity` pending deliveries and starts
+    /// the worker thread.
+    pub fn new(capacity: usize, policy: OverflowPolicy) -> DeliveryQueue {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                jobs: VecDeque::with_capacity(capacity),
+                in_flight: false,
+                shutdown: false,
+            }),
+            changed: Condvar::new(),
+        });
+
+        let worker_shared = shared.clone();
+        thread::Builder::new()
+            .name("bugsnag-delivery".to_owned())
+            .spawn(move || run_worker(&worker_shared))
+            .expect("failed to spawn the bugsnag delivery thread");
+
+        DeliveryQueue {
+            shared,
+            capacity: capacity.max(1),
+            policy,
+        }
+    }
+
+    /// Adds a delivery to the queue. If the queue is full, the overflow policy decides
+    /// which delivery is dropped. `Error::DeliveryQueueFull` is returned if the given
+    /// delivery was dropped.
+    pub fn push(&self, job: Job) -> Result<(), Error> {
+        let mut state = self.shared.lock();
+
+        while state.jobs.len() >= self.capacity {
+            match self.policy {
+                OverflowPolicy::DropOldest => {
+                    state.jobs.pop_front();
+                }
+                OverflowPolicy::DropNewest => return Err(Error::DeliveryQueueFull),
+                OverflowPolicy::Block => {
+                    state = self.shared
+                        .changed
+                        .wait(state)
+                        .unwrap_or_else(|e| e.into_inner());
+                }
+            }
+        }
+
+        state.jobs.push_back(job);
+        self.shared.changed.notify_all();
+        Ok(())
+    }
+
+    /// Waits until all pending deliveries are executed or the timeout elapsed.
+    /// Returns `true` if all deliveries were executed.
+    pub fn flush(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.shared.lock();
+
+        while !state.jobs.is_empty() || state.in_flight {
+            let now = Instant::now();
+            if now >= deadline {
+                return false;
+            }
+
+            state = self.shared
+                .changed
+                .wait_timeout(state, deadline - now)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+
+        true
+    }
+}
+
+impl Drop for DeliveryQueue {
+    /// Stops the worker thread after all pending deliveries are executed. Use
+    /// `flush` to wait for the pending deliveries.
+    fn drop(&mut self) {
+        self.shared.lock().shutdown = true;
+        self.shared.changed.notify_all();
+    }
+}
+
+fn run_worker(shared: &Shared) {
+    loop {
+        let job = {
+            let mut state = shared.lock();
+            state.in_flight = false;
+            shared.changed.notify_all();
+
+            loop {
+                if let Some(job) = state.jobs.pop_front() {
+                    state.in_flight = true;
+                    shared.changed.notify_all();
+                    break job;
+                }
+
+                if state.shutdown {
+                    return;
+                }
+
+                state = shared.changed.wait(state).unwrap_or_else(|e| e.into_inner());
+            }
+        };
+
+        // a failing delivery should not stop the worker
+        let _ = panic::catch_unwind(AssertUnwindSafe(job));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_status, Delivery, DeliveryQueue, OverflowPolicy};
+    use super::super::Error;
+    use super::super::outbox::Outbox;
+    use super::super::retry::RetryPolicy;
+    use super::super::transport::{Response, Transport};
+    use std::sync::{mpsc, Arc, Mutex};
+    use std::time::Duration;
+    use std::{env, fs, process, thread};
+
+    /// A transport that blocks the first request until the returned sender is dropped.
+    struct BlockingTransport {
+        started: Mutex<Option<mpsc::Sender<()>>>,
+        release: Mutex<mpsc::Receiver<()>>,
+        requests: Mutex<Vec<String>>,
+    }
+
+    impl BlockingTransport {
+        fn new() -> (Arc<BlockingTransport>, mpsc::Receiver<()>, mpsc::Sender<()>) {
+            let (started_tx, started_rx) = mpsc::channel();
+            let (release_tx, release_rx) = mpsc::channel();
+            let transport = Arc::new(BlockingTransport {
+                started: Mutex::new(Some(started_tx)),
+                release: Mutex::new(release_rx),
+                requests: Mutex::new(Vec::new()),
+            });
+            (transport, started_rx, release_tx)
+        }
+    }
+
+    impl Transport for BlockingTransport {
+        fn send(&self, _: &str, _: &[(&str, &str)], body: &str) -> Result<Response, Error> {
+            self.requests.lock().unwrap().push(body.to_owned());
+            if let Some(started) = self.started.lock().unwrap().take() {
+                started.send(()).unwrap();
+                let _ = self.release.lock().unwrap().recv();
+            }
+            Ok(Response::new(200))
+        }
+    }
+
+    /// A transport whose HTTP client could not be created.
+    struct TlsFailureTransport;
+
+    impl Transport for TlsFailureTransport {
+        fn send(&self, _: &str, _: &[(&str, &str)], _: &str) -> Result<Response, Error> {
+            Err(Error::TlsInitFailed("no TLS library".to_owned()))
+        }
+    }
+
+    /// Pushes a job that blocks the worker until the returned sender is dropped.
+    fn block_worker(queue: &DeliveryQueue) -> mpsc::Sender<()> {
+        let (started_tx, started_rx) = mpsc::channel();
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+        queue
+            .push(Box::new(move || {
+                started_tx.send(()).unwrap();
+                let _ = release_rx.recv();
+            }))
+            .unwrap();
+        started_rx.recv().unwrap();
+        release_tx
+    }
+
+    fn push_recording(queue: &DeliveryQueue, log: &Arc<Mutex<Vec<u32>>>, value: u32) -> bool {
+        let log = log.clone();
+        queue
+            .push(Box::new(move || log.lock().unwrap().push(value)))
+            .is_ok()
+    }
+
+    #[test]
+    fn test_flush_executes_all_jobs() {
+        let queue = DeliveryQueue::new(10, OverflowPolicy::DropNewest);
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        for value in 0..5 {
+            assert!(push_recording(&queue, &log, value));
+        }
+
+        assert!(queue.flush(Duration::from_secs(5)));
+        assert_eq!(*log.lock().unwrap(), vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_flush_times_out() {
+        let queue = DeliveryQueue::new(10, OverflowPolicy::DropNewest);
+        let release = block_worker(&queue);
+
+        assert!(!queue.flush(Duration::from_millis(10)));
+        drop(release);
+        assert!(queue.flush(Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn test_drop_newest() {
+        let queue = DeliveryQueue::new(2, OverflowPolicy::DropNewest);
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let release = block_worker(&queue);
+
+        assert!(push_recording(&queue, &log, 1));
+        assert!(push_recording(&queue, &log, 2));
+        assert_eq!(
+            queue.push(Box::new(|| {})).unwrap_err(),
+            Error::DeliveryQueueFull
+        );
+
+        drop(release);
+        assert!(queue.flush(Duration::from_secs(5)));
+        assert_eq!(*log.lock().unwrap(), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_drop_oldest() {
+        let queue = DeliveryQueue::new(2, OverflowPolicy::DropOldest);
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let release = block_worker(&queue);
+
+        assert!(push_recording(&queue, &log, 1));
+        assert!(push_recording(&queue, &log, 2));
+        assert!(push_recording(&queue, &log, 3));
+
+        drop(release);
+        assert!(queue.flush(Duration::from_secs(5)));
+        assert_eq!(*log.lock().unwrap(), vec![2, 3]);
+    }
+
+    #[test]
+    fn test_block() {
+        let queue = Arc::new(DeliveryQueue::new(1, OverflowPolicy::Block));
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let release = block_worker(&queue);
+
+        assert!(push_recording(&queue, &log, 1));
+
+        let (queue2, log2) = (queue.clone(), log.clone());
+        let pusher = ::std::thread::spawn(move || push_recording(&queue2, &log2, 2));
+
+        drop(release);
+        assert!(pusher.join().unwrap());
+        assert!(queue.flush(Duration::from_secs(5)));
+        assert_eq!(*log.lock().unwrap(), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_send_outbox_skips_notifications_that_are_sent() {
+        let dir = env::temp_dir().join(format!("bugsnag-delivery-claim-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let (transport, started, release) = BlockingTransport::new();
+        let delivery = Delivery {
+            transport: transport.clone(),
+            retry_policy: RetryPolicy::none(),
+            url: "http://localhost:9000".to_owned(),
+            api_key: "api-key".to_owned(),
+            payload_version: "4".to_owned(),
+            outbox: Some(Arc::new(Outbox::new(&dir))),
+        };
+
+        let sender = delivery.clone();
+        let persistent = thread::spawn(move || sender.send_persistent("{}"));
+        started.recv().unwrap();
+
+        // a sweep while the notification is sent does not send it again
+        assert_eq!(delivery.send_outbox(), Ok(0));
+        drop(release);
+        assert_eq!(persistent.join().unwrap(), Ok(()));
+        assert_eq!(transport.requests.lock().unwrap().len(), 1);
+        assert_eq!(delivery.send_outbox(), Ok(0));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_keep_notifications_if_tls_init_failed() {
+        let dir = env::temp_dir().join(format!("bugsnag-delivery-tls-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let outbox = Arc::new(Outbox::new(&dir));
+        let delivery = Delivery {
+            transport: Arc::new(TlsFailureTransport),
+            retry_policy: RetryPolicy::none(),
+            url: "http://localhost:9000".to_owned(),
+            api_key: "api-key".to_owned(),
+            payload_version: "4".to_owned(),
+            outbox: Some(outbox.clone()),
+        };
+
+        let error = Err(Error::TlsInitFailed("no TLS library".to_owned()));
+        assert_eq!(delivery.send_persistent("{}"), error);
+        assert_eq!(delivery.send_outbox(), error.map(|_| 0));
+        assert_eq!(outbox.pending().unwrap().len(), 1);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_check_status() {
+        assert_eq!(check_status(200), Ok(()));
+        assert_eq!(check_status(202), Ok(()));
+        assert_eq!(check_status(400), Err(Error::BadRequest));
+        assert_eq!(check_status(401), Err(Error::Unauthorized));
+        assert_eq!(check_status(413), Err(Error::PayloadTooLarge));
+        assert_eq!(check_status(429), Err(Error::RateLimited));
+        assert_eq!(check_status(503), Err(Error::ServerError(503)));
+        assert_eq!(check_status(302), Err(Error::UnexpectedStatus(302)));
+    }
+}
@@ -1,6 +1,6 @@
 use sys_info;
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DeviceInfo {
     os_version: String,
@@ -17,7 +17,7 @@ impl DeviceInfo {
 
     pub fn generate() -> DeviceInfo {
         let mut version = sys_info::os_type().unwrap_or("Unknown".to_owned());
-        version.push_str(":");
+        version.push(':');
         version.push_str(&sys_info::os_release().unwrap_or("u.k.n.o.w.n".to_owned()));
 
         let hostname = sys_info::hostname().unwrap_or("UnknownHost".to_owned());
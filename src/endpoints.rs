@@ -0,0 +1,101 @@
+//! Module for the urls of the Bugsnag api.
+
+use std::env;
+
+/// The default endpoint for notifications.
+pub const DEFAULT_NOTIFY_ENDPOINT: &str = "https://notify.bugsnag.com";
+/// The default endpoint for sessions.
+pub const DEFAULT_SESSIONS_ENDPOINT: &str = "https://sessions.bugsnag.com";
+
+/// Environment variable that overwrites the default notify endpoint.
+pub const NOTIFY_ENDPOINT_ENV: &str = "BUGSNAG_NOTIFY_ENDPOINT";
+/// Environment variable that overwrites the default sessions endpoint.
+pub const SESSIONS_ENDPOINT_ENV: &str = "BUGSNAG_SESSIONS_ENDPOINT";
+
+/// The urls the notifications and sessions are send to. Change these urls to use
+/// Bugsnag On-Premise or a local test server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Endpoints {
+    notify: String,
+    sessions: String,
+}
+
+impl Endpoints {
+    pub fn new(notify: &str, sessions: &str) -> Endpoints {
+        Endpoints {
+            notify: notify.to_owned(),
+            sessions: sessions.to_owned(),
+        }
+    }
+
+    /// Creates the endpoints from the environment variables `BUGSNAG_NOTIFY_ENDPOINT`
+    /// and `BUGSNAG_SESSIONS_ENDPOINT`. The public Bugsnag endpoints are used for
+    /// variables that are not set.
+    pub fn from_env() -> Endpoints {
+        Endpoints::from_lookup(|name| env::var(name).ok())
+    }
+
+    fn from_lookup<F: Fn(&str) -> Option<String>>(lookup: F) -> Endpoints {
+        let notify = lookup(NOTIFY_ENDPOINT_ENV).filter(|url| !url.is_empty());
+        let sessions = lookup(SESSIONS_ENDPOINT_ENV).filter(|url| !url.is_empty());
+
+        Endpoints {
+            notify: notify.unwrap_or_else(|| DEFAULT_NOTIFY_ENDPOINT.to_owned()),
+            sessions: sessions.unwrap_or_else(|| DEFAULT_SESSIONS_ENDPOINT.to_owned()),
+        }
+    }
+
+    pub fn notify(&self) -> &str {
+        &self.notify
+    }
+
+    pub fn sessions(&self) -> &str {
+        &self.sessions
+    }
+}
+
+impl Default for Endpoints {
+    fn default() -> Endpoints {
+        Endpoints::new(DEFAULT_NOTIFY_ENDPOINT, DEFAULT_SESSIONS_ENDPOINT)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Endpoints, DEFAULT_NOTIFY_ENDPOINT, DEFAULT_SESSIONS_ENDPOINT};
+
+    #[test]
+    fn test_default_endpoints_use_https() {
+        let endpoints = Endpoints::default();
+
+        assert_eq!(endpoints.notify(), "https://notify.bugsnag.com");
+        assert_eq!(endpoints.sessions(), "https://sessions.bugsnag.com");
+    }
+
+    #[test]
+    fn test_endpoints_from_lookup() {
+        let endpoints = Endpoints::from_lookup(|name| match name {
+            "BUGSNAG_NOTIFY_ENDPOINT" => Some("http://localhost:9000".to_owned()),
+            "BUGSNAG_SESSIONS_ENDPOINT" => Some("http://localhost:9001".to_owned()),
+            _ => None,
+        });
+
+        assert_eq!(
+            endpoints,
+            Endpoints::new("http://localhost:9000", "http://localhost:9001")
+        );
+    }
+
+    #[test]
+    fn test_endpoints_from_empty_lookup() {
+        let endpoints = Endpoints::from_lookup(|name| match name {
+            "BUGSNAG_NOTIFY_ENDPOINT" => Some(String::new()),
+            _ => None,
+        });
+
+        assert_eq!(
+            endpoints,
+            Endpoints::new(DEFAULT_NOTIFY_ENDPOINT, DEFAULT_SESSIONS_ENDPOINT)
+        );
+    }
+}
@@ -1,7 +1,12 @@
 use super::exception::Exception;
-use super::Severity;
+use super::{Severity, SeverityReason};
 use super::deviceinfo::DeviceInfo;
 use super::appinfo::AppInfo;
+use super::metadata::MetaData;
+use super::user::User;
+use super::breadcrumbs::Breadcrumb;
+use super::session::Session;
+use super::threads::Thread;
 
 pub const PAYLOAD_VERSION: u32 = 4;
 
@@ -11,10 +16,17 @@ pub struct Event<'a> {
     payload_version: u32,
     exceptions: &'a [Exception<'a>],
     #[serde(skip_serializing_if = "Option::is_none")] severity: Option<&'a Severity>,
+    #[serde(skip_serializing_if = "Option::is_none")] unhandled: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")] severity_reason: Option<&'a SeverityReason>,
     #[serde(skip_serializing_if = "Option::is_none")] context: Option<&'a str>,
     device: &'a DeviceInfo,
     #[serde(skip_serializing_if = "Option::is_none")] app: &'a Option<AppInfo>,
-    #[serde(skip_serializing_if = "Option::is_none")] group_hash: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")] grouping_hash: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")] meta_data: Option<&'a MetaData>,
+    #[serde(skip_serializing_if = "Option::is_none")] user: Option<&'a User>,
+    #[serde(skip_serializing_if = "Option::is_none")] breadcrumbs: Option<&'a [Breadcrumb]>,
+    #[serde(skip_serializing_if = "Option::is_none")] session: Option<&'a Session>,
+    #[serde(skip_serializing_if = "Option::is_none")] threads: Option<&'a [Thread]>,
 }
 
 impl<'a> Event<'a> {
@@ -22,7 +34,7 @@ impl<'a> Event<'a> {
         exceptions: &'a [Exception],
         severity: Option<&'a Severity>,
         context: Option<&'a str>,
-        group_hash: Option<&'a str>,
+        grouping_hash: Option<&'a str>,
         device: &'a DeviceInfo,
         app: &'a Option<AppInfo>,
     ) -> Event<'a> {
@@ -30,17 +42,56 @@ impl<'a> Event<'a> {
             payload_version: PAYLOAD_VERSION,
             exceptions,
             severity,
+            unhandled: None,
+            severity_reason: None,
             context,
             device,
             app,
-            group_hash,
+            grouping_hash,
+            meta_data: None,
+            user: None,
+            breadcrumbs: None,
+            session: None,
+            threads: None,
         }
     }
+
+    /// Sets the custom diagnostic data of the event.
+    pub fn set_metadata(&mut self, metadata: &'a MetaData) {
+        self.meta_data = Some(metadata);
+    }
+
+    /// Sets the user that was affected by the event.
+    pub fn set_user(&mut self, user: &'a User) {
+        self.user = Some(user);
+    }
+
+    /// Sets the breadcrumbs that led up to the event.
+    pub fn set_breadcrumbs(&mut self, breadcrumbs: &'a [Breadcrumb]) {
+        self.breadcrumbs = Some(breadcrumbs);
+    }
+
+    /// Sets the reason for the severity, which also decides if the event is unhandled.
+    pub fn set_severity_reason(&mut self, severity_reason: &'a SeverityReason) {
+        self.unhandled = Some(severity_reason.is_unhandled());
+        self.severity_reason = Some(severity_reason);
+    }
+
+    /// Sets the session the event belongs to.
+    pub fn set_session(&mut self, session: &'a Session) {
+        self.session = Some(session);
+    }
+
+    /// Sets the threads of the process at the time of the event.
+    pub fn set_threads(&mut self, threads: &'a [Thread]) {
+        self.threads = Some(threads);
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{AppInfo, DeviceInfo, Event, Severity, PAYLOAD_VERSION};
+    use super::{AppInfo, DeviceInfo, Event, MetaData, Severity, SeverityReason, User,
+                PAYLOAD_VERSION};
     use serde_test::{assert_ser_tokens, Token};
 
     #[test]
@@ -140,6 +191,43 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_event_with_grouping_hash_to_json() {
+        let empty_vec = Vec::new();
+        let device = DeviceInfo::new("1.0.0", "testmachine");
+        let app = None;
+        let evt = Event::new(&empty_vec, None, None, Some("my-hash"), &device, &app);
+
+        assert_ser_tokens(
+            &evt,
+            &[
+                Token::Struct {
+                    name: "Event",
+                    len: 4,
+                },
+                Token::Str("payloadVersion"),
+                Token::U32(PAYLOAD_VERSION),
+                Token::Str("exceptions"),
+                Token::Seq { len: Some(0) },
+                Token::SeqEnd,
+                Token::Str("device"),
+                Token::Struct {
+                    name: "DeviceInfo",
+                    len: 2,
+                },
+                Token::Str("osVersion"),
+                Token::Str("1.0.0"),
+                Token::Str("hostname"),
+                Token::Str("testmachine"),
+                Token::StructEnd,
+                Token::Str("groupingHash"),
+                Token::Some,
+                Token::Str("my-hash"),
+                Token::StructEnd,
+            ],
+        );
+    }
+
     #[test]
     fn test_event_with_app_info_to_json() {
         let empty_vec = Vec::new();
@@ -202,4 +290,144 @@ mod tests {
             ],
         );
     }
+
+    #[test]
+    fn test_event_with_metadata_to_json() {
+        let empty_vec = Vec::new();
+        let device = DeviceInfo::new("1.0.0", "testmachine");
+        let app = None;
+        let mut metadata = MetaData::new();
+        metadata.set_tab("tab", "text").unwrap();
+        let mut evt = Event::new(&empty_vec, None, None, None, &device, &app);
+        evt.set_metadata(&metadata);
+
+        assert_ser_tokens(
+            &evt,
+            &[
+                Token::Struct {
+                    name: "Event",
+                    len: 4,
+                },
+                Token::Str("payloadVersion"),
+                Token::U32(PAYLOAD_VERSION),
+                Token::Str("exceptions"),
+                Token::Seq { len: Some(0) },
+                Token::SeqEnd,
+                Token::Str("device"),
+                Token::Struct {
+                    name: "DeviceInfo",
+                    len: 2,
+                },
+                Token::Str("osVersion"),
+                Token::Str("1.0.0"),
+                Token::Str("hostname"),
+                Token::Str("testmachine"),
+                Token::StructEnd,
+                Token::Str("metaData"),
+                Token::Some,
+                Token::Map { len: Some(1) },
+                Token::Str("tab"),
+                Token::Map { len: Some(1) },
+                Token::Str("value"),
+                Token::Str("text"),
+                Token::MapEnd,
+                Token::MapEnd,
+                Token::StructEnd,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_event_with_user_to_json() {
+        let empty_vec = Vec::new();
+        let device = DeviceInfo::new("1.0.0", "testmachine");
+        let app = None;
+        let user = User::new(Some("123"), None, None);
+        let mut evt = Event::new(&empty_vec, None, None, None, &device, &app);
+        evt.set_user(&user);
+
+        assert_ser_tokens(
+            &evt,
+            &[
+                Token::Struct {
+                    name: "Event",
+                    len: 4,
+                },
+                Token::Str("payloadVersion"),
+                Token::U32(PAYLOAD_VERSION),
+                Token::Str("exceptions"),
+                Token::Seq { len: Some(0) },
+                Token::SeqEnd,
+                Token::Str("device"),
+                Token::Struct {
+                    name: "DeviceInfo",
+                    len: 2,
+                },
+                Token::Str("osVersion"),
+                Token::Str("1.0.0"),
+                Token::Str("hostname"),
+                Token::Str("testmachine"),
+                Token::StructEnd,
+                Token::Str("user"),
+                Token::Some,
+                Token::Struct {
+                    name: "User",
+                    len: 1,
+                },
+                Token::Str("id"),
+                Token::Some,
+                Token::Str("123"),
+                Token::StructEnd,
+                Token::StructEnd,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_event_with_severity_reason_to_json() {
+        let empty_vec = Vec::new();
+        let device = DeviceInfo::new("1.0.0", "testmachine");
+        let app = None;
+        let reason = SeverityReason::UnhandledPanic;
+        let mut evt = Event::new(&empty_vec, None, None, None, &device, &app);
+        evt.set_severity_reason(&reason);
+
+        assert_ser_tokens(
+            &evt,
+            &[
+                Token::Struct {
+                    name: "Event",
+                    len: 5,
+                },
+                Token::Str("payloadVersion"),
+                Token::U32(PAYLOAD_VERSION),
+                Token::Str("exceptions"),
+                Token::Seq { len: Some(0) },
+                Token::SeqEnd,
+                Token::Str("unhandled"),
+                Token::Some,
+                Token::Bool(true),
+                Token::Str("severityReason"),
+                Token::Some,
+                Token::Struct {
+                    name: "SeverityReason",
+                    len: 1,
+                },
+                Token::Str("type"),
+                Token::Str("unhandledPanic"),
+                Token::StructEnd,
+                Token::Str("device"),
+                Token::Struct {
+                    name: "DeviceInfo",
+                    len: 2,
+                },
+                Token::Str("osVersion"),
+                Token::Str("1.0.0"),
+                Token::Str("hostname"),
+                Token::Str("testmachine"),
+                Token::StructEnd,
+                Token::StructEnd,
+            ],
+        );
+    }
 }
@@ -1,5 +1,12 @@
 use super::stacktrace::Frame;
 
+use std::any;
+use std::error::Error as StdError;
+use std::{fmt, io, num, str, string};
+
+/// The error class of errors whose concrete type is unknown.
+const UNKNOWN_CLASS: &str = "Error";
+
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Exception<'a> {
@@ -12,15 +19,141 @@ impl<'a> Exception<'a> {
     pub fn new(errorclass: &'a str, message: &'a str, stacktrace: &'a [Frame]) -> Exception<'a> {
         Exception {
             error_class: errorclass,
-            message: message,
-            stacktrace: stacktrace,
+            message,
+            stacktrace,
         }
     }
 }
 
+/// Returns the error classes of the errors of the application, e.g. for the causes of an
+/// error, whose type is not known by the notifier.
+///
+/// The trait is implemented for closures, e.g.
+///
+/// ```
+/// # use std::error::Error;
+/// # #[derive(Debug)]
+/// # struct DatabaseError;
+/// # impl std::fmt::Display for DatabaseError {
+/// #     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+/// #         write!(f, "database error")
+/// #     }
+/// # }
+/// # impl Error for DatabaseError {}
+/// let mut api = bugsnag::Bugsnag::new("api-key", env!("CARGO_MANIFEST_DIR"));
+/// api.set_error_classifier(|error: &(dyn Error + 'static)| {
+///     if error.is::<DatabaseError>() {
+///         Some("DatabaseError".to_owned())
+///     } else {
+///         None
+///     }
+/// });
+/// ```
+pub trait ErrorClassifier: Send + Sync {
+    /// Returns the error class of the error, or `None` if the error is unknown.
+    fn error_class(&self, error: &(dyn StdError + 'static)) -> Option<String>;
+}
+
+impl<F> ErrorClassifier for F
+where
+    F: Fn(&(dyn StdError + 'static)) -> Option<String> + Send + Sync,
+{
+    fn error_class(&self, error: &(dyn StdError + 'static)) -> Option<String> {
+        self(error)
+    }
+}
+
+/// Returns the error class and the message of the given error and of all its sources,
+/// starting with the given error.
+///
+/// The error class of the given error is the name of its type. The sources are trait
+/// objects, so their class is returned by the classifier, or is the name of the type for
+/// the errors of the standard library. All other errors, and the given error if it is a
+/// trait object, have the error class `Error`.
+pub fn error_chain<E: StdError + ?Sized>(
+    error: &E,
+    classifier: Option<&dyn ErrorClassifier>,
+) -> Vec<(String, String)> {
+    let type_name = any::type_name::<E>();
+    let class = std_errors()
+        .iter()
+        .find(|std_error| (std_error.type_name)() == type_name)
+        .map(|std_error| std_error.class.to_owned())
+        .or_else(|| class_from_type_name(type_name))
+        .unwrap_or_else(|| UNKNOWN_CLASS.to_owned());
+    let mut result = vec![(class, error.to_string())];
+
+    let mut source = error.source();
+    while let Some(error) = source {
+        result.push((class_of_source(error, classifier), error.to_string()));
+        source = error.source();
+    }
+
+    result
+}
+
+/// Extracts the name of a type without its module path and generic parameters.
+/// Returns `None` for trait objects, because their type name does not contain
+/// the name of the concrete type.
+fn class_from_type_name(name: &str) -> Option<String> {
+    if name.starts_with("dyn ") || name.starts_with('&') {
+        return None;
+    }
+
+    let name = name.split('<').next().unwrap_or(name);
+    name.rsplit("::").next().map(|name| name.to_owned())
+}
+
+/// Returns the error class of an error source. The classifier takes precedence over the
+/// names of the errors of the standard library.
+fn class_of_source(
+    error: &(dyn StdError + 'static),
+    classifier: Option<&dyn ErrorClassifier>,
+) -> String {
+    classifier
+        .and_then(|classifier| classifier.error_class(error))
+        .or_else(|| {
+            std_errors()
+                .iter()
+                .find(|std_error| (std_error.is)(error))
+                .map(|std_error| std_error.class.to_owned())
+        })
+        .unwrap_or_else(|| UNKNOWN_CLASS.to_owned())
+}
+
+/// An error of the standard library, that is reported with the path of its type, because
+/// multiple of these types are called `Error`.
+struct StdErrorClass {
+    is: fn(&(dyn StdError + 'static)) -> bool,
+    type_name: fn() -> &'static str,
+    class: &'static str,
+}
+
+fn std_error<T: StdError + 'static>(class: &'static str) -> StdErrorClass {
+    StdErrorClass {
+        is: |error| error.is::<T>(),
+        type_name: any::type_name::<T>,
+        class,
+    }
+}
+
+fn std_errors() -> [StdErrorClass; 7] {
+    [
+        std_error::<io::Error>("std::io::Error"),
+        std_error::<fmt::Error>("std::fmt::Error"),
+        std_error::<num::ParseIntError>("std::num::ParseIntError"),
+        std_error::<num::ParseFloatError>("std::num::ParseFloatError"),
+        std_error::<num::TryFromIntError>("std::num::TryFromIntError"),
+        std_error::<str::Utf8Error>("std::str::Utf8Error"),
+        std_error::<string::FromUtf8Error>("std::string::FromUtf8Error"),
+    ]
+}
+
 #[cfg(test)]
 mod tests {
-    use super::Exception;
+    use super::{error_chain, ErrorClassifier, Exception};
+    use std::error::Error as StdError;
+    use std::{fmt, io};
     use serde_test::{assert_ser_tokens, Token};
 
     #[test]
@@ -46,4 +179,119 @@ mod tests {
             ],
         );
     }
+
+    #[derive(Debug)]
+    struct ConfigError {
+        source: ParseError,
+    }
+
+    #[derive(Debug)]
+    struct ParseError {
+        source: ::std::num::ParseIntError,
+    }
+
+    impl fmt::Display for ConfigError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "could not load the config")
+        }
+    }
+
+    impl StdError for ConfigError {
+        fn source(&self) -> Option<&(dyn StdError + 'static)> {
+            Some(&self.source)
+        }
+    }
+
+    impl fmt::Display for ParseError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "could not parse the port")
+        }
+    }
+
+    impl StdError for ParseError {
+        fn source(&self) -> Option<&(dyn StdError + 'static)> {
+            Some(&self.source)
+        }
+    }
+
+    fn config_error() -> ConfigError {
+        ConfigError {
+            source: ParseError {
+                source: "port".parse::<u16>().unwrap_err(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_error_chain() {
+        let chain = error_chain(&config_error(), None);
+
+        assert_eq!(
+            chain,
+            vec![
+                ("ConfigError".to_owned(), "could not load the config".to_owned()),
+                ("Error".to_owned(), "could not parse the port".to_owned()),
+                (
+                    "std::num::ParseIntError".to_owned(),
+                    "invalid digit found in string".to_owned(),
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_error_chain_of_trait_object() {
+        let error = config_error();
+        let error: &dyn StdError = &error;
+        let chain = error_chain(error, None);
+
+        assert_eq!(chain.len(), 3);
+        assert_eq!(chain[0].0, "Error");
+        assert_eq!(chain[2].0, "std::num::ParseIntError");
+    }
+
+    #[test]
+    fn test_error_chain_with_classifier() {
+        let classifier = |error: &(dyn StdError + 'static)| {
+            if error.is::<ParseError>() {
+                Some("ParseError".to_owned())
+            } else {
+                None
+            }
+        };
+        let chain = error_chain(&config_error(), Some(&classifier as &dyn ErrorClassifier));
+
+        let classes: Vec<_> = chain.iter().map(|(class, _)| class.as_str()).collect();
+        assert_eq!(classes, vec!["ConfigError", "ParseError", "std::num::ParseIntError"]);
+    }
+
+    #[derive(Debug)]
+    struct WriteError {
+        source: io::Error,
+    }
+
+    impl fmt::Display for WriteError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "could not write the report")
+        }
+    }
+
+    impl StdError for WriteError {
+        fn source(&self) -> Option<&(dyn StdError + 'static)> {
+            Some(&self.source)
+        }
+    }
+
+    #[test]
+    fn test_error_chain_of_std_errors() {
+        let error = WriteError {
+            source: io::Error::other("disk full"),
+        };
+        let chain = error_chain(&error, None);
+        assert_eq!(chain[1].0, "std::io::Error");
+
+        let chain = error_chain(&io::Error::other("disk full"), None);
+        assert_eq!(chain[0].0, "std::io::Error");
+        assert_eq!(error_chain(&fmt::Error, None)[0].0, "std::fmt::Error");
+    }
 }
@@ -0,0 +1,239 @@
+//! Module for deciding which frames of a stacktrace belong to the project.
+
+use regex::Regex;
+use std::env;
+
+use super::path_normalizer::strip_dir;
+use super::stacktrace::strip_hashes;
+
+/// Path patterns of the sources of dependencies that cargo downloaded.
+const CARGO_SOURCE_PATTERNS: &[&str] = &["*/.cargo/registry/*", "*/.cargo/git/checkouts/*"];
+
+/// Decides if a frame belongs to the project, so that Bugsnag can hide the frames of
+/// dependencies in the web interface.
+///
+/// A frame belongs to the project if its file is in one of the source roots or its method
+/// belongs to one of the project crates, and it is not excluded. Frames in the sources that
+/// cargo downloaded, e.g. in `~/.cargo/registry` or `$CARGO_HOME/registry`, are always
+/// excluded.
+///
+/// Crate names and paths are matched with glob patterns, where `*` matches any number of
+/// characters and `?` matches one character.
+///
+/// # Example
+///
+/// ```
+/// let filter = bugsnag::ProjectFrameFilter::new()
+///     .source_root(env!("CARGO_MANIFEST_DIR"))
+///     .crate_name("my_company_*")
+///     .exclude_path("*/vendor/*");
+///
+/// assert!(filter.is_in_project("/src/lib.rs", "my_company_core::run"));
+/// assert!(!filter.is_in_project("/src/vendor/lib.rs", "my_company_core::run"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ProjectFrameFilter {
+    source_roots: Vec<String>,
+    crate_names: Vec<Regex>,
+    excluded_paths: Vec<Regex>,
+    excluded_methods: Vec<String>,
+}
+
+impl ProjectFrameFilter {
+    /// Creates a filter without any source roots or crates, so no frame belongs to
+    /// the project.
+    pub fn new() -> ProjectFrameFilter {
+        let mut excluded_paths: Vec<_> = CARGO_SOURCE_PATTERNS.iter().map(|p| glob(p)).collect();
+        if let Ok(cargo_home) = env::var("CARGO_HOME") {
+            excluded_paths.push(glob(&format!("{}/registry/*", cargo_home)));
+            excluded_paths.push(glob(&format!("{}/git/*", cargo_home)));
+        }
+
+        ProjectFrameFilter {
+            source_roots: Vec::new(),
+            crate_names: Vec::new(),
+            excluded_paths,
+            excluded_methods: Vec::new(),
+        }
+    }
+
+    /// Adds a directory with sources of the project.
+    pub fn source_root(mut self, dir: &str) -> Self {
+        self.source_roots.push(dir.to_owned());
+        self
+    }
+
+    /// Adds a glob pattern for the names of the project crates, e.g. `my_company_*`.
+    /// Crate names use underscores instead of hyphens.
+    pub fn crate_name(mut self, pattern: &str) -> Self {
+        self.crate_names.push(glob(pattern));
+        self
+    }
+
+    /// Adds a glob pattern for paths that do not belong to the project, even if they are
+    /// in a source root, e.g. `*/vendor/*`.
+    pub fn exclude_path(mut self, pattern: &str) -> Self {
+        self.excluded_paths.push(glob(pattern));
+        self
+    }
+
+    /// Excludes all methods that contain the given string.
+    pub fn exclude_method(mut self, method: &str) -> Self {
+        self.excluded_methods.push(method.to_owned());
+        self
+    }
+
+    /// Returns if the frame with the given file and method belongs to the project.
+    pub fn is_in_project(&self, file: &str, method: &str) -> bool {
+        let in_source_root = self.source_roots
+            .iter()
+            .any(|dir| !dir.is_empty() && strip_dir(file, dir).is_some());
+        let in_project_crate = !self.crate_names.is_empty() && {
+            let method = strip_hashes(method);
+            let name = crate_name(&method);
+            self.crate_names.iter().any(|pattern| pattern.is_match(name))
+        };
+
+        (in_source_root || in_project_crate)
+            && !self.excluded_paths.iter().any(|pattern| pattern.is_match(file))
+            && !self.excluded_methods
+                .iter()
+                .any(|excluded| method.contains(excluded.as_str()))
+    }
+}
+
+impl Default for ProjectFrameFilter {
+    fn default() -> ProjectFrameFilter {
+        ProjectFrameFilter::new()
+    }
+}
+
+/// Returns the name of the crate a method belongs to, e.g. `my_crate` for
+/// `<my_crate::Type as core::fmt::Debug>::fmt`.
+fn crate_name(method: &str) -> &str {
+    let method = method.trim_start_matches('<');
+    method.split("::").next().unwrap_or(method)
+}
+
+/// Converts a glob pattern into a regex that matches the whole string.
+fn glob(pattern: &str) -> Regex {
+    let mut regex = String::from("^");
+    for c in pattern.chars() {
+        match c {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+
+    Regex::new(&regex).expect("an escaped glob pattern is a valid regex")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{crate_name, ProjectFrameFilter};
+
+    #[test]
+    fn test_empty_filter() {
+        let filter = ProjectFrameFilter::new();
+
+        assert!(!filter.is_in_project("/project/src/main.rs", "my_app::main"));
+    }
+
+    #[test]
+    fn test_source_roots() {
+        let filter = ProjectFrameFilter::new()
+            .source_root("/project/app")
+            .source_root("/project/lib");
+
+        assert!(filter.is_in_project("/project/app/src/main.rs", "my_app::main"));
+        assert!(filter.is_in_project("/project/lib/src/lib.rs", "my_lib::run"));
+        assert!(!filter.is_in_project("/usr/src/main.rs", "my_app::main"));
+    }
+
+    #[test]
+    fn test_source_root_excludes_sibling_dirs() {
+        let filter = ProjectFrameFilter::new().source_root("/project/app/");
+
+        assert!(filter.is_in_project("/project/app/src/main.rs", "my_app::main"));
+        assert!(!filter.is_in_project("/project/app2/src/main.rs", "my_app::main"));
+        assert!(!filter.is_in_project("/project/application.rs", "my_app::main"));
+    }
+
+    #[test]
+    fn test_empty_source_root_matches_nothing() {
+        let filter = ProjectFrameFilter::new().source_root("");
+
+        assert!(!filter.is_in_project("/project/src/main.rs", "my_app::main"));
+    }
+
+    #[test]
+    fn test_crate_names() {
+        let filter = ProjectFrameFilter::new().crate_name("my_company_*");
+
+        assert!(filter.is_in_project("src/lib.rs", "my_company_core::run::h0123456789abcdef"));
+        assert!(filter.is_in_project(
+            "src/lib.rs",
+            "<my_company_core::Error as core::fmt::Debug>::fmt"
+        ));
+        assert!(!filter.is_in_project("src/lib.rs", "other_crate::run"));
+        assert!(!filter.is_in_project("src/lib.rs", "core::my_company_core::run"));
+    }
+
+    #[test]
+    fn test_excluded_paths() {
+        let filter = ProjectFrameFilter::new()
+            .source_root("/project")
+            .exclude_path("*/vendor/*");
+
+        assert!(filter.is_in_project("/project/src/main.rs", "my_app::main"));
+        assert!(!filter.is_in_project("/project/vendor/zlib/lib.rs", "zlib::inflate"));
+    }
+
+    #[test]
+    fn test_cargo_sources_are_excluded() {
+        let filter = ProjectFrameFilter::new()
+            .source_root("/home/ci")
+            .crate_name("serde*");
+
+        assert!(!filter.is_in_project(
+            "/home/ci/.cargo/registry/src/index.crates.io-6f17d22bba15001f/serde-1.0.0/src/de.rs",
+            "serde::de::Deserialize::deserialize"
+        ));
+        assert!(!filter.is_in_project(
+            "/home/ci/.cargo/git/checkouts/serde-0123456789abcdef/src/de.rs",
+            "serde::de::Deserialize::deserialize"
+        ));
+    }
+
+    #[test]
+    fn test_excluded_methods() {
+        let filter = ProjectFrameFilter::new()
+            .source_root("/project")
+            .exclude_method("my_app::logger")
+            .exclude_method("my_app::report");
+
+        assert!(filter.is_in_project("/project/src/main.rs", "my_app::main"));
+        assert!(!filter.is_in_project("/project/src/main.rs", "my_app::logger::log"));
+        assert!(!filter.is_in_project("/project/src/main.rs", "my_app::report::send"));
+    }
+
+    #[test]
+    fn test_glob_escapes_regex() {
+        let filter = ProjectFrameFilter::new()
+            .source_root("/project")
+            .exclude_path("/project/gen?rated/*.rs");
+
+        assert!(!filter.is_in_project("/project/generated/api.rs", "my_app::api"));
+        assert!(filter.is_in_project("/project/generated/api_rs", "my_app::api"));
+        assert!(filter.is_in_project("/project/src/lib.rs", "my_app::api"));
+    }
+
+    #[test]
+    fn test_crate_name() {
+        assert_eq!(crate_name("my_app::main"), "my_app");
+        assert_eq!(crate_name("<my_app::Type as core::fmt::Debug>::fmt"), "my_app");
+        assert_eq!(crate_name("main"), "main");
+    }
+}
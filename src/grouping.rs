@@ -0,0 +1,260 @@
+//! Module for computing the grouping hash, that decides which notifications Bugsnag
+//! groups together as the same error.
+
+use regex::Regex;
+
+use super::stacktrace::Frame;
+
+/// The reported error, that a grouping hash is computed for.
+pub struct GroupedError<'a> {
+    error_class: &'a str,
+    message: &'a str,
+    frames: &'a [Frame],
+}
+
+impl<'a> GroupedError<'a> {
+    pub fn new(error_class: &'a str, message: &'a str, frames: &'a [Frame]) -> GroupedError<'a> {
+        GroupedError {
+            error_class,
+            message,
+            frames,
+        }
+    }
+
+    pub fn error_class(&self) -> &'a str {
+        self.error_class
+    }
+
+    pub fn message(&self) -> &'a str {
+        self.message
+    }
+
+    /// Returns the method and the file of the frames that belong to the project, the
+    /// top frame first. The line numbers are left out, because they change with
+    /// every release.
+    pub fn in_project_frames(&self) -> impl Iterator<Item = (&'a str, &'a str)> {
+        self.frames
+            .iter()
+            .filter(|frame| frame.in_project())
+            .map(|frame| (frame.method(), frame.file()))
+    }
+}
+
+/// Computes the grouping hash of the reported errors. Bugsnag groups the notifications with
+/// the same hash together, the notifications without a hash are grouped by Bugsnag, e.g.
+/// by the error class and the top frame of the stacktrace.
+///
+/// The trait is implemented for closures, e.g.
+///
+/// ```
+/// let mut api = bugsnag::Bugsnag::new("api-key", env!("CARGO_MANIFEST_DIR"));
+/// api.set_grouping_strategy(|error: &bugsnag::GroupedError| {
+///     Some(error.error_class().to_owned())
+/// });
+/// ```
+pub trait GroupingStrategy: Send + Sync {
+    /// Returns the grouping hash of the error, or `None` to let Bugsnag group the error.
+    fn grouping_hash(&self, error: &GroupedError) -> Option<String>;
+}
+
+impl<F> GroupingStrategy for F
+where
+    F: Fn(&GroupedError) -> Option<String> + Send + Sync,
+{
+    fn grouping_hash(&self, error: &GroupedError) -> Option<String> {
+        self(error)
+    }
+}
+
+/// Groups the errors by the error class, the message and the top frames that belong to
+/// the project.
+///
+/// The parts of the message that usually change with every occurrence, like ids, are
+/// masked: UUIDs, hexadecimal numbers like `0x7ffd5c0` and decimal numbers. So the messages
+/// `user 42 not found` and `user 43 not found` belong to the same group.
+///
+/// # Example
+///
+/// ```
+/// let api = bugsnag::BugsnagBuilder::new("api-key", env!("CARGO_MANIFEST_DIR"))
+///     .grouping_strategy(bugsnag::FingerprintGrouping::new().frames(1))
+///     .build();
+/// ```
+#[derive(Debug, Clone)]
+pub struct FingerprintGrouping {
+    message: bool,
+    frames: usize,
+    masks: Vec<(Regex, &'static str)>,
+}
+
+impl FingerprintGrouping {
+    /// Creates a strategy that uses the masked message and the top three frames that
+    /// belong to the project.
+    pub fn new() -> FingerprintGrouping {
+        let masks = [
+            (
+                r"[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}",
+                "<uuid>",
+            ),
+            (r"0[xX][0-9a-fA-F]+", "<hex>"),
+            (r"[0-9]+", "<num>"),
+        ];
+
+        FingerprintGrouping {
+            message: true,
+            frames: 3,
+            masks: masks
+                .iter()
+                .map(|&(pattern, mask)| {
+                    (Regex::new(pattern).expect("the masks are valid regexes"), mask)
+                })
+                .collect(),
+        }
+    }
+
+    /// Sets if the masked message is part of the hash.
+    pub fn message(mut self, message: bool) -> Self {
+        self.message = message;
+        self
+    }
+
+    /// Sets the number of top frames that belong to the project, that are part of the hash.
+    pub fn frames(mut self, frames: usize) -> Self {
+        self.frames = frames;
+        self
+    }
+
+    /// Returns the message with the masked UUIDs and numbers.
+    fn mask(&self, message: &str) -> String {
+        self.masks
+            .iter()
+            .fold(message.to_owned(), |message, &(ref pattern, mask)| {
+                pattern.replace_all(&message, mask).into_owned()
+            })
+    }
+}
+
+impl Default for FingerprintGrouping {
+    fn default() -> FingerprintGrouping {
+        FingerprintGrouping::new()
+    }
+}
+
+impl GroupingStrategy for FingerprintGrouping {
+    fn grouping_hash(&self, error: &GroupedError) -> Option<String> {
+        let mut fingerprint = vec![error.error_class().to_owned()];
+        if self.message {
+            fingerprint.push(self.mask(error.message()));
+        }
+        for (method, file) in error.in_project_frames().take(self.frames) {
+            fingerprint.push(format!("{} {}", method, file));
+        }
+
+        Some(format!("{:016x}", fnv1a(fingerprint.join("\n").as_bytes())))
+    }
+}
+
+/// The 64 bit FNV-1a hash, which is the same on every platform and in every release,
+/// unlike the hasher of the standard library.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fnv1a, FingerprintGrouping, GroupedError, GroupingStrategy};
+    use super::super::stacktrace::Frame;
+
+    fn frames() -> Vec<Frame> {
+        vec![
+            Frame::new("src/db.rs", 10, "my_app::db::query", true),
+            Frame::new("serde-1.0.0/src/de.rs", 20, "serde::de::deserialize", false),
+            Frame::new("src/users.rs", 30, "my_app::users::find", true),
+            Frame::new("src/main.rs", 40, "my_app::main", true),
+        ]
+    }
+
+    #[test]
+    fn test_fnv1a() {
+        assert_eq!(fnv1a(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+    }
+
+    #[test]
+    fn test_mask_message() {
+        let grouping = FingerprintGrouping::new();
+
+        assert_eq!(
+            grouping.mask("user 42 not found in 5a9c9e1e-1d3b-4c1a-9f7e-3a5b8c6d2e10"),
+            "user <num> not found in <uuid>"
+        );
+        assert_eq!(
+            grouping.mask("invalid pointer 0x7ffd5c0 at offset 12"),
+            "invalid pointer <hex> at offset <num>"
+        );
+        assert_eq!(grouping.mask("no numbers"), "no numbers");
+    }
+
+    #[test]
+    fn test_same_group_for_masked_messages() {
+        let grouping = FingerprintGrouping::new();
+        let frames = frames();
+
+        let hash = |error_class, message| {
+            grouping.grouping_hash(&GroupedError::new(error_class, message, &frames))
+        };
+
+        assert_eq!(hash("NotFound", "user 42"), hash("NotFound", "user 43"));
+        assert_ne!(hash("NotFound", "user 42"), hash("NotFound", "group 42"));
+        assert_ne!(hash("NotFound", "user 42"), hash("Forbidden", "user 42"));
+        assert_eq!(hash("NotFound", "user 42").unwrap().len(), 16);
+    }
+
+    #[test]
+    fn test_top_in_project_frames() {
+        fn error(frames: &[Frame]) -> GroupedError<'_> {
+            GroupedError::new("NotFound", "user 42", frames)
+        }
+        let mut frames = frames();
+        let grouping = FingerprintGrouping::new().frames(2);
+
+        let hash = grouping.grouping_hash(&error(&frames));
+        assert_eq!(
+            error(&frames).in_project_frames().collect::<Vec<_>>(),
+            vec![
+                ("my_app::db::query", "src/db.rs"),
+                ("my_app::users::find", "src/users.rs"),
+                ("my_app::main", "src/main.rs"),
+            ]
+        );
+
+        // only the top two frames of the project are part of the hash
+        frames[3] = Frame::new("src/main.rs", 41, "my_app::run", true);
+        assert_eq!(grouping.grouping_hash(&error(&frames)), hash);
+        frames[2] = Frame::new("src/users.rs", 31, "my_app::users::get", true);
+        assert_ne!(grouping.grouping_hash(&error(&frames)), hash);
+    }
+
+    #[test]
+    fn test_without_message() {
+        let frames = frames();
+        let grouping = FingerprintGrouping::new().message(false);
+
+        assert_eq!(
+            grouping.grouping_hash(&GroupedError::new("NotFound", "user", &frames)),
+            grouping.grouping_hash(&GroupedError::new("NotFound", "group", &frames))
+        );
+    }
+
+    #[test]
+    fn test_closure_strategy() {
+        let strategy = |error: &GroupedError| Some(error.error_class().to_lowercase());
+
+        assert_eq!(
+            strategy.grouping_hash(&GroupedError::new("NotFound", "user", &[])),
+            Some("notfound".to_owned())
+        );
+    }
+}
@@ -19,8 +19,22 @@
 //! For more examples on how to integrate bugsnag into a project, the examples
 //! folder provides some reference implementations.
 
+#[cfg(feature = "symbolicate")]
+extern crate addr2line;
 extern crate backtrace;
+#[cfg(feature = "hyper-transport")]
 extern crate hyper;
+#[cfg(target_os = "linux")]
+extern crate libc;
+#[cfg(feature = "hyper-transport")]
+extern crate native_tls;
+#[cfg(feature = "ureq-transport")]
+extern crate ureq;
+#[cfg(feature = "reqwest-transport")]
+extern crate reqwest;
+extern crate object;
+extern crate regex;
+extern crate rustc_demangle;
 extern crate serde;
 #[macro_use]
 extern crate serde_derive;
@@ -28,13 +42,55 @@ extern crate serde_json;
 #[cfg(test)]
 extern crate serde_test;
 extern crate sys_info;
+extern crate uuid;
 
 mod event;
 mod notification;
 mod stacktrace;
+pub use self::stacktrace::{Symbolication, TrimRules};
+mod memory_map;
+mod frame_filter;
+pub use self::frame_filter::ProjectFrameFilter;
+mod path_normalizer;
+pub use self::path_normalizer::PathNormalizer;
+mod snippets;
+pub use self::snippets::CodeSnippets;
+mod method_names;
+pub use self::method_names::MethodNames;
+mod grouping;
+pub use self::grouping::{FingerprintGrouping, GroupedError, GroupingStrategy};
 mod exception;
+pub use self::exception::ErrorClassifier;
+mod report;
+pub use self::report::Report;
+mod redaction;
+pub use self::redaction::RedactedKeys;
 mod bugsnag_impl;
 pub use self::bugsnag_impl::*;
 mod deviceinfo;
 mod appinfo;
+mod metadata;
+pub mod user;
+pub use self::user::User;
+mod breadcrumbs;
+pub use self::breadcrumbs::{Breadcrumb, BreadcrumbType};
+mod timestamp;
+mod delivery;
+pub use self::delivery::OverflowPolicy;
+mod endpoints;
+pub use self::endpoints::Endpoints;
+mod builder;
+pub use self::builder::BugsnagBuilder;
+pub mod transport;
+pub use self::transport::Transport;
+mod retry;
+pub use self::retry::RetryPolicy;
+mod outbox;
+pub use self::outbox::Outbox;
+mod session;
+mod threads;
+#[cfg(feature = "symbolicate")]
+mod symbolicate;
+#[cfg(feature = "symbolicate")]
+pub use self::symbolicate::{SymbolicationError, Symbolicator};
 pub mod panic;
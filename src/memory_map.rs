@@ -0,0 +1,227 @@
+//! Module for finding the object file, i.e. the executable or a shared library, that
+//! contains an address of the process.
+
+use object::{self, Object, ObjectSegment, ReadCache};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// The size of a memory page, object files are mapped at page boundaries.
+const PAGE_SIZE: u64 = 0x1000;
+
+/// The build ids of the object files that were read, by path.
+static BUILD_IDS: Mutex<BTreeMap<String, Option<String>>> = Mutex::new(BTreeMap::new());
+
+/// A range of memory that is mapped from an object file.
+#[derive(Debug, Clone, PartialEq)]
+struct Mapping {
+    start: usize,
+    end: usize,
+    path: String,
+}
+
+/// The object files that are mapped into the memory of the process.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MemoryMap {
+    mappings: Vec<Mapping>,
+}
+
+/// An object file that contains an address.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadedObject<'a> {
+    /// The address the object file was loaded at.
+    pub load_address: usize,
+    pub path: &'a str,
+}
+
+impl MemoryMap {
+    /// Reads the memory map of the current process. The memory map is only known on Linux,
+    /// on all other platforms it is empty.
+    pub fn current() -> MemoryMap {
+        #[cfg(target_os = "linux")]
+        {
+            if let Ok(maps) = fs::read_to_string("/proc/self/maps") {
+                return MemoryMap::parse(&maps);
+            }
+        }
+
+        MemoryMap::default()
+    }
+
+    /// Parses a memory map in the format of `/proc/<pid>/maps`.
+    pub fn parse(maps: &str) -> MemoryMap {
+        let mappings = maps.lines()
+            .filter_map(|line| {
+                // 55d0c1a00000-55d0c1a26000 r--p 00000000 fd:01 1234   /usr/bin/app
+                let mut fields = line.splitn(6, ' ');
+                let mut range = fields.next()?.splitn(2, '-');
+                let start = usize::from_str_radix(range.next()?, 16).ok()?;
+                let end = usize::from_str_radix(range.next()?, 16).ok()?;
+                let path = fields.nth(4)?.trim_start();
+
+                // anonymous memory and special mappings like `[stack]` have no file
+                if path.starts_with('/') {
+                    Some(Mapping {
+                        start,
+                        end,
+                        path: path.to_owned(),
+                    })
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        MemoryMap { mappings }
+    }
+
+    /// Returns the object file that contains the address.
+    pub fn find(&self, address: usize) -> Option<LoadedObject<'_>> {
+        let mapping = self.mappings
+            .iter()
+            .find(|mapping| mapping.start <= address && address < mapping.end)?;
+
+        // the object file is loaded at the start of its first mapping
+        let load_address = self.mappings
+            .iter()
+            .filter(|other| other.path == mapping.path)
+            .map(|other| other.start)
+            .min()
+            .unwrap_or(mapping.start);
+
+        Some(LoadedObject {
+            load_address,
+            path: &mapping.path,
+        })
+    }
+}
+
+/// The properties of an object file, that are required to symbolicate its addresses.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectInfo {
+    /// The build id as hex string, which identifies the build of the object file.
+    pub build_id: Option<String>,
+    /// The address of the first segment in the object file. The object file is loaded
+    /// at the page that contains this address.
+    pub base_address: u64,
+}
+
+impl ObjectInfo {
+    /// Reads the properties of an object file, which can be a separate debug file.
+    /// Returns `None` if the file is not a supported object file.
+    pub fn read(path: &Path) -> Option<ObjectInfo> {
+        let file = fs::File::open(path).ok()?;
+        let cache = ReadCache::new(file);
+        let object = object::File::parse(&cache).ok()?;
+
+        let build_id = object.build_id().ok()?.map(format_build_id);
+        let base_address = object
+            .segments()
+            .map(|segment| segment.address())
+            .min()
+            .unwrap_or(0);
+
+        Some(ObjectInfo {
+            build_id,
+            base_address: base_address & !(PAGE_SIZE - 1),
+        })
+    }
+}
+
+/// Returns the build id of the object file at the given path. The build id is only read
+/// once per object file.
+pub fn build_id(path: &str) -> Option<String> {
+    let mut build_ids = BUILD_IDS.lock().unwrap_or_else(|e| e.into_inner());
+    build_ids
+        .entry(path.to_owned())
+        .or_insert_with(|| ObjectInfo::read(Path::new(path)).and_then(|info| info.build_id))
+        .clone()
+}
+
+fn format_build_id(build_id: &[u8]) -> String {
+    build_id.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build_id, format_build_id, LoadedObject, MemoryMap, ObjectInfo};
+    use std::path::Path;
+
+    const MAPS: &str = "\
+55d0c1a00000-55d0c1a26000 r--p 00000000 fd:01 1234                       /usr/bin/app
+55d0c1a26000-55d0c1b00000 r-xp 00026000 fd:01 1234                       /usr/bin/app
+55d0c2000000-55d0c2100000 rw-p 00000000 00:00 0                          [heap]
+7f1e2a000000-7f1e2a028000 r--p 00000000 fd:01 5678                       /usr/lib/libc.so.6
+7f1e2a028000-7f1e2a1bd000 r-xp 00028000 fd:01 5678                       /usr/lib/libc.so.6
+7f1e2a300000-7f1e2a321000 rw-p 00000000 00:00 0
+7ffd5c000000-7ffd5c021000 rw-p 00000000 00:00 0                          [stack]
+";
+
+    #[test]
+    fn test_find_object() {
+        let map = MemoryMap::parse(MAPS);
+
+        assert_eq!(
+            map.find(0x55d0c1a30000),
+            Some(LoadedObject {
+                load_address: 0x55d0c1a00000,
+                path: "/usr/bin/app",
+            })
+        );
+        assert_eq!(
+            map.find(0x7f1e2a028000),
+            Some(LoadedObject {
+                load_address: 0x7f1e2a000000,
+                path: "/usr/lib/libc.so.6",
+            })
+        );
+    }
+
+    #[test]
+    fn test_find_without_object() {
+        let map = MemoryMap::parse(MAPS);
+
+        assert_eq!(map.find(0x55d0c2000010), None);
+        assert_eq!(map.find(0x7f1e2a300000), None);
+        assert_eq!(map.find(0x1000), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_current_contains_this_function() {
+        let map = MemoryMap::current();
+        let address = test_current_contains_this_function as *const () as usize;
+
+        let object = map.find(address).unwrap();
+        assert!(object.load_address <= address);
+        assert_eq!(
+            object.path,
+            ::std::env::current_exe().unwrap().to_str().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_format_build_id() {
+        assert_eq!(format_build_id(&[0x0a, 0xbc, 0x00, 0xff]), "0abc00ff");
+    }
+
+    #[test]
+    fn test_read_invalid_object() {
+        let source = concat!(env!("CARGO_MANIFEST_DIR"), "/src/memory_map.rs");
+
+        assert_eq!(ObjectInfo::read(Path::new(source)), None);
+        assert_eq!(ObjectInfo::read(Path::new("/does/not/exist")), None);
+        assert_eq!(build_id("/does/not/exist"), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_read_current_exe() {
+        let exe = ::std::env::current_exe().unwrap();
+
+        let info = ObjectInfo::read(&exe).unwrap();
+        assert_eq!(info.base_address % 0x1000, 0);
+        assert_eq!(build_id(exe.to_str().unwrap()), info.build_id);
+    }
+}
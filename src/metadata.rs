@@ -0,0 +1,235 @@
+//! Module for the custom diagnostic data (the `metaData` field) of an event.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use serde_json::{self, Map, Value};
+
+use super::Error;
+
+/// Custom diagnostic data, grouped into named tabs. Every tab is shown as a
+/// separate tab in the Bugsnag web interface.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct MetaData {
+    tabs: BTreeMap<String, Value>,
+}
+
+impl MetaData {
+    pub fn new() -> MetaData {
+        MetaData::default()
+    }
+
+    /// Converts the given value to json and stores it in the given tab.
+    /// An existing tab with the same name is replaced.
+    ///
+    /// Values that are not converted to a json object are stored under the key
+    /// `value` in the tab, because Bugsnag expects every tab to be an object.
+    pub fn set_tab<T: Serialize + ?Sized>(&mut self, tab: &str, value: &T) -> Result<(), Error> {
+        let value = match serde_json::to_value(value) {
+            Ok(Value::Object(map)) => Value::Object(map),
+            Ok(value) => {
+                let mut map = Map::new();
+                map.insert("value".to_owned(), value);
+                Value::Object(map)
+            }
+            Err(_) => return Err(Error::JsonConversionFailed),
+        };
+
+        self.tabs.insert(tab.to_owned(), value);
+        Ok(())
+    }
+
+    /// Returns the values of the given tab.
+    pub fn tab(&self, tab: &str) -> Option<&Value> {
+        self.tabs.get(tab)
+    }
+
+    /// Removes the given tab.
+    pub fn remove_tab(&mut self, tab: &str) {
+        self.tabs.remove(tab);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tabs.is_empty()
+    }
+
+    /// Merges `other` into a copy of this object. Tabs that exist in both objects
+    /// are merged recursively: nested objects are merged key by key, all other values
+    /// of `other` take precedence.
+    pub fn merge(&self, other: &MetaData) -> MetaData {
+        let mut result = self.clone();
+
+        for (tab, value) in &other.tabs {
+            match result.tabs.get_mut(tab) {
+                Some(existing) => merge_values(existing, value),
+                None => {
+                    result.tabs.insert(tab.clone(), value.clone());
+                }
+            }
+        }
+
+        result
+    }
+}
+
+/// Merges `other` into `target`. Objects are merged key by key, other values are replaced.
+fn merge_values(target: &mut Value, other: &Value) {
+    if let (Value::Object(existing), Value::Object(values)) = (&mut *target, other) {
+        for (key, value) in values {
+            match existing.get_mut(key) {
+                Some(existing) => merge_values(existing, value),
+                None => {
+                    existing.insert(key.clone(), value.clone());
+                }
+            }
+        }
+        return;
+    }
+
+    *target = other.clone();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MetaData;
+    use std::collections::BTreeMap;
+    use serde_json::json;
+    use serde_test::{assert_ser_tokens, Token};
+
+    #[derive(Serialize)]
+    struct Request {
+        url: &'static str,
+        status: u32,
+    }
+
+    #[test]
+    fn test_metadata_to_json() {
+        let mut metadata = MetaData::new();
+        metadata
+            .set_tab(
+                "request",
+                &Request {
+                    url: "/index",
+                    status: 500,
+                },
+            )
+            .unwrap();
+
+        assert_ser_tokens(
+            &metadata,
+            &[
+                Token::Map { len: Some(1) },
+                Token::Str("request"),
+                Token::Map { len: Some(2) },
+                Token::Str("status"),
+                Token::U64(500),
+                Token::Str("url"),
+                Token::Str("/index"),
+                Token::MapEnd,
+                Token::MapEnd,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_metadata_non_object_value() {
+        let mut metadata = MetaData::new();
+        metadata.set_tab("tab", "text").unwrap();
+
+        assert_eq!(
+            ::serde_json::to_string(&metadata).unwrap(),
+            r#"{"tab":{"value":"text"}}"#
+        );
+    }
+
+    #[test]
+    fn test_metadata_remove_tab() {
+        let mut metadata = MetaData::new();
+        metadata.set_tab("tab", "text").unwrap();
+        metadata.remove_tab("tab");
+
+        assert!(metadata.is_empty());
+    }
+
+    #[test]
+    fn test_metadata_merge() {
+        let mut global = MetaData::new();
+        global
+            .set_tab(
+                "request",
+                &Request {
+                    url: "/global",
+                    status: 200,
+                },
+            )
+            .unwrap();
+        global.set_tab("global", "value").unwrap();
+
+        let mut local = MetaData::new();
+        local
+            .set_tab(
+                "request",
+                &Request {
+                    url: "/local",
+                    status: 500,
+                },
+            )
+            .unwrap();
+        local.set_tab("local", "value").unwrap();
+
+        assert_eq!(
+            ::serde_json::to_string(&global.merge(&local)).unwrap(),
+            r#"{"global":{"value":"value"},"local":{"value":"value"},"request":{"status":500,"url":"/local"}}"#
+        );
+    }
+
+    #[test]
+    fn test_metadata_merge_keeps_global_keys() {
+        let mut global = MetaData::new();
+        global
+            .set_tab(
+                "request",
+                &Request {
+                    url: "/global",
+                    status: 200,
+                },
+            )
+            .unwrap();
+
+        let mut values = BTreeMap::new();
+        values.insert("url", "/local");
+        let mut local = MetaData::new();
+        local.set_tab("request", &values).unwrap();
+
+        assert_eq!(
+            ::serde_json::to_string(&global.merge(&local)).unwrap(),
+            r#"{"request":{"status":200,"url":"/local"}}"#
+        );
+    }
+
+    #[test]
+    fn test_metadata_merge_nested_objects() {
+        let mut global = MetaData::new();
+        global
+            .set_tab(
+                "request",
+                &json!({"headers": {"Accept": "text/html", "Host": "example.com"}, "ids": [1]}),
+            )
+            .unwrap();
+        let mut local = MetaData::new();
+        local
+            .set_tab("request", &json!({"headers": {"Accept": "application/json"}, "ids": [2]}))
+            .unwrap();
+
+        assert_eq!(
+            ::serde_json::to_value(global.merge(&local)).unwrap(),
+            json!({
+                "request": {
+                    "headers": {"Accept": "application/json", "Host": "example.com"},
+                    "ids": [2]
+                }
+            })
+        );
+    }
+}
@@ -0,0 +1,248 @@
+//! Module for converting the symbol names of the frames into method names, that do not
+//! change between builds.
+
+use rustc_demangle;
+
+use super::stacktrace::strip_hashes;
+
+/// Cleans up the method names of the frames, so that the same method has the same name in
+/// every build and with every toolchain. Bugsnag groups the errors by their stacktrace, so
+/// names that change between builds would split one error into many.
+///
+/// Symbol names of the legacy and the v0 mangling are demangled, and:
+///
+/// * the hash suffix like `::h4a7e324bba05a745` and crate disambiguators like
+///   `std[e28293b1aa0f68bd]` are removed, unless `strip_hashes` is disabled
+/// * closures are named `{{closure}}`, instead of `{closure#0}` by the v0 mangling
+/// * trait implementations are written as `<Type as Trait>`, instead of
+///   `<impl Trait for Type>`
+///
+/// With `simplify_closures`, the closures are removed from the names, so that
+/// `my_app::main::{{closure}}` becomes `my_app::main`. This groups errors in closures of
+/// the same function together, even if closures are added or removed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MethodNames {
+    strip_hashes: bool,
+    simplify_closures: bool,
+}
+
+impl MethodNames {
+    /// Creates the default settings: hashes are removed, closures are kept.
+    pub fn new() -> MethodNames {
+        MethodNames {
+            strip_hashes: true,
+            simplify_closures: false,
+        }
+    }
+
+    /// Sets if the hashes are removed from the names.
+    pub fn strip_hashes(mut self, strip_hashes: bool) -> Self {
+        self.strip_hashes = strip_hashes;
+        self
+    }
+
+    /// Sets if the closures are removed from the names.
+    pub fn simplify_closures(mut self, simplify_closures: bool) -> Self {
+        self.simplify_closures = simplify_closures;
+        self
+    }
+
+    /// Returns the cleaned up name of a method, which can also be a mangled symbol name.
+    pub fn clean(&self, method: &str) -> String {
+        let mut method = demangle(method);
+        if self.strip_hashes {
+            method = strip_hashes(&method);
+        }
+
+        method = normalize_closures(&method);
+        if self.simplify_closures {
+            method = method.replace("::{{closure}}", "");
+        }
+        normalize_impls(&method)
+    }
+}
+
+impl Default for MethodNames {
+    fn default() -> MethodNames {
+        MethodNames::new()
+    }
+}
+
+/// Demangles a symbol name of the legacy or the v0 mangling. Names that are not mangled
+/// are returned unchanged.
+pub fn demangle(symbol: &str) -> String {
+    match rustc_demangle::try_demangle(symbol) {
+        // the alternate format omits the hash suffix and the crate disambiguators
+        Ok(demangled) => format!("{:#}", demangled),
+        Err(_) => symbol.to_owned(),
+    }
+}
+
+/// Names the closures `{{closure}}`, like the legacy mangling does.
+fn normalize_closures(method: &str) -> String {
+    let mut result = String::with_capacity(method.len());
+    let mut rest = method;
+
+    while let Some(start) = rest.find("{closure#") {
+        result.push_str(&rest[..start]);
+
+        let after = &rest[start + "{closure#".len()..];
+        let digits = after.find(|c: char| !c.is_ascii_digit()).unwrap_or(after.len());
+        if digits > 0 && after[digits..].starts_with('}') {
+            result.push_str("{{closure}}");
+            rest = &after[digits + 1..];
+        } else {
+            result.push_str("{closure#");
+            rest = after;
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Converts the trait implementations `<impl Trait for Type>` into `<Type as Trait>` and
+/// the inherent implementations `<impl Type>` into `<Type>`.
+fn normalize_impls(method: &str) -> String {
+    let start = match method.find("<impl ") {
+        Some(start) => start,
+        None => return method.to_owned(),
+    };
+    let inner_start = start + "<impl ".len();
+
+    // the end of the implementation, generic arguments are nested in `<` and `>`, the
+    // `>` of a return type like `Fn() -> u32` is not a bracket
+    let mut depth = 0;
+    let mut end = None;
+    let mut separator = None;
+    let mut previous = None;
+    for (index, c) in method[inner_start..].char_indices() {
+        let index = inner_start + index;
+        let arrow = previous == Some('-');
+        previous = Some(c);
+        match c {
+            '>' if arrow => {}
+            '<' => depth += 1,
+            '>' if depth == 0 => {
+                end = Some(index);
+                break;
+            }
+            '>' => depth -= 1,
+            _ if depth == 0 && separator.is_none() && method[index..].starts_with(" for ") => {
+                separator = Some(index)
+            }
+            _ => {}
+        }
+    }
+
+    let end = match end {
+        Some(end) => end,
+        None => return method.to_owned(),
+    };
+    let implementation = match separator {
+        Some(separator) => format!(
+            "<{} as {}>",
+            &method[separator + " for ".len()..end],
+            &method[inner_start..separator]
+        ),
+        None => format!("<{}>", &method[inner_start..end]),
+    };
+
+    format!(
+        "{}{}{}",
+        &method[..start],
+        implementation,
+        normalize_impls(&method[end + 1..])
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{demangle, MethodNames};
+
+    #[test]
+    fn test_demangle_legacy() {
+        assert_eq!(
+            demangle("_ZN8my_crate4main17h0123456789abcdefE"),
+            "my_crate::main"
+        );
+        assert_eq!(demangle("my_crate::main"), "my_crate::main");
+        assert_eq!(demangle("__libc_start_main"), "__libc_start_main");
+    }
+
+    #[test]
+    fn test_demangle_v0() {
+        assert_eq!(demangle("_RNvC6_123foo3bar"), "123foo::bar");
+        assert_eq!(
+            demangle("_RNCNCNgCs6DXkGYLi8lr_2cc5spawn00B5_"),
+            "cc::spawn::{closure#0}::{closure#0}"
+        );
+    }
+
+    #[test]
+    fn test_clean_is_the_same_for_all_manglings() {
+        let names = MethodNames::new();
+
+        assert_eq!(
+            names.clean("cc::spawn::{closure#0}::{closure#0}"),
+            names.clean("cc::spawn::{{closure}}::{{closure}}::h0123456789abcdef")
+        );
+        assert_eq!(
+            names.clean("_ZN8my_crate4main17h0123456789abcdefE"),
+            names.clean("my_crate::main::h0123456789abcdef")
+        );
+        assert_eq!(
+            names.clean("std[e28293b1aa0f68bd]::panicking::panic_with_hook"),
+            "std::panicking::panic_with_hook"
+        );
+    }
+
+    #[test]
+    fn test_keep_hashes() {
+        let names = MethodNames::new().strip_hashes(false);
+
+        assert_eq!(
+            names.clean("my_crate::main::h0123456789abcdef"),
+            "my_crate::main::h0123456789abcdef"
+        );
+    }
+
+    #[test]
+    fn test_simplify_closures() {
+        let names = MethodNames::new().simplify_closures(true);
+
+        assert_eq!(names.clean("cc::spawn::{closure#0}::{closure#1}"), "cc::spawn");
+        assert_eq!(names.clean("my_app::main::{{closure}}::run"), "my_app::main::run");
+        assert_eq!(
+            MethodNames::new().clean("my_app::main::{closure#12}"),
+            "my_app::main::{{closure}}"
+        );
+        assert_eq!(MethodNames::new().clean("my_app::{closure#x}"), "my_app::{closure#x}");
+    }
+
+    #[test]
+    fn test_normalize_impls() {
+        let names = MethodNames::new();
+
+        assert_eq!(
+            names.clean("<impl core::fmt::Debug for my_app::Error>::fmt"),
+            "<my_app::Error as core::fmt::Debug>::fmt"
+        );
+        assert_eq!(
+            names.clean("<impl core::convert::From<alloc::vec::Vec<u8>> for my_app::Buf<T>>::from"),
+            "<my_app::Buf<T> as core::convert::From<alloc::vec::Vec<u8>>>::from"
+        );
+        assert_eq!(names.clean("<impl my_app::Error>::new"), "<my_app::Error>::new");
+        assert_eq!(
+            names.clean("<impl Fn() -> u32 for my_app::Task>::call"),
+            "<my_app::Task as Fn() -> u32>::call"
+        );
+        assert_eq!(
+            names.clean("<impl my_app::Run<fn(u8) -> u8> for my_app::Task>::run"),
+            "<my_app::Task as my_app::Run<fn(u8) -> u8>>::run"
+        );
+        assert_eq!(
+            names.clean("<my_app::Error as core::fmt::Debug>::fmt"),
+            "<my_app::Error as core::fmt::Debug>::fmt"
+        );
+    }
+}
@@ -1,17 +1,27 @@
 use super::event::Event;
 
-const NOTIFIER_NAME: &'static str = "Bugsnag Rust";
-const NOTIFIER_VERSION: &'static str = env!("CARGO_PKG_VERSION");
-const NOTIFIER_URL: &'static str = "https://github.com/superscale/bugsnag-api-rs";
+const NOTIFIER_NAME: &str = "Bugsnag Rust";
+const NOTIFIER_VERSION: &str = env!("CARGO_PKG_VERSION");
+const NOTIFIER_URL: &str = "https://github.com/superscale/bugsnag-api-rs";
 
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
-struct Notifier {
+pub struct Notifier {
     name: &'static str,
     version: &'static str,
     url: &'static str,
 }
 
+impl Default for Notifier {
+    fn default() -> Notifier {
+        Notifier {
+            name: NOTIFIER_NAME,
+            version: NOTIFIER_VERSION,
+            url: NOTIFIER_URL,
+        }
+    }
+}
+
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Notification<'a> {
@@ -24,12 +34,8 @@ impl<'a> Notification<'a> {
     pub fn new(apikey: &'a str, events: &'a [Event]) -> Notification<'a> {
         Notification {
             api_key: apikey,
-            notifier: Notifier {
-                name: NOTIFIER_NAME,
-                version: NOTIFIER_VERSION,
-                url: NOTIFIER_URL,
-            },
-            events: events,
+            notifier: Notifier::default(),
+            events,
         }
     }
 }
@@ -0,0 +1,482 @@
+//! Module for storing notifications that could not be delivered on disk, so that
+//! they can be delivered later.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The extension of the notifications that are pending.
+pub const EXTENSION: &str = "json";
+
+/// The extension of the notifications whose stacktraces are not symbolicated yet. They
+/// are only pending after the symbolication.
+pub const UNRESOLVED_EXTENSION: &str = "unresolved";
+
+/// The extension of the notifications that are being sent.
+const CLAIMED_EXTENSION: &str = "sending";
+
+/// The time after which a claimed notification is pending again, if it is not known whether
+/// the process that claimed it is still running.
+const CLAIM_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+/// A directory that stores the json of notifications that could not be delivered.
+///
+/// Every notification is stored in a separate file. The oldest files are removed
+/// if the outbox exceeds one of its limits.
+///
+/// Notifications with a deferred symbolication are stored as unresolved, so that they are
+/// not sent before their stacktraces are symbolicated, e.g. by the `bugsnag-symbolicate`
+/// tool, which makes them pending.
+///
+/// A notification is claimed before it is sent, by renaming its file, so that it is not
+/// sent twice by concurrent deliveries. The claimed notifications are not pending, until
+/// the process that claimed them exited without sending them.
+#[derive(Debug)]
+pub struct Outbox {
+    dir: PathBuf,
+    max_files: usize,
+    max_total_size: u64,
+    max_age: Duration,
+    counter: AtomicUsize,
+}
+
+impl Outbox {
+    /// Creates an outbox in the given directory. The directory is created when the
+    /// first notification is stored.
+    ///
+    /// By default, the outbox holds up to 100 notifications with a total size of
+    /// 10 MiB, that are not older than 7 days.
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Outbox {
+        Outbox {
+            dir: dir.into(),
+            max_files: 100,
+            max_total_size: 10 * 1024 * 1024,
+            max_age: Duration::from_secs(7 * 24 * 60 * 60),
+            counter: AtomicUsize::new(0),
+        }
+    }
+
+    /// Sets the maximum number of stored notifications.
+    pub fn max_files(mut self, max_files: usize) -> Self {
+        self.max_files = max_files;
+        self
+    }
+
+    /// Sets the maximum size in bytes of all stored notifications.
+    pub fn max_total_size(mut self, max_total_size: u64) -> Self {
+        self.max_total_size = max_total_size;
+        self
+    }
+
+    /// Sets the maximum age of a stored notification. Older notifications are removed.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Stores the json of a notification and returns the path of the created file.
+    pub fn store(&self, json: &str) -> io::Result<PathBuf> {
+        let name = self.next_name();
+        self.write(json, &self.dir.join(format!("{}.{}", name, EXTENSION)))
+    }
+
+    /// Stores the json of a notification whose stacktraces are not symbolicated yet. The
+    /// notification is not pending until it is symbolicated.
+    pub fn store_unresolved(&self, json: &str) -> io::Result<PathBuf> {
+        let name = self.next_name();
+        self.write(json, &self.dir.join(format!("{}.{}", name, UNRESOLVED_EXTENSION)))
+    }
+
+    /// Stores the json of a notification that is claimed by the caller, because it is
+    /// sent right away. Returns the path of the claimed file.
+    pub fn store_claimed(&self, json: &str) -> io::Result<PathBuf> {
+        let name = self.next_name();
+        self.write(json, &claimed_path(&self.dir, &name))
+    }
+
+    /// Returns the paths of the stored notifications, the oldest notification first.
+    /// Notifications that exceed the maximum age are removed.
+    pub fn pending(&self) -> io::Result<Vec<PathBuf>> {
+        self.release_expired_claims()?;
+        self.enforce_limits()?;
+        Ok(self.entries(EXTENSION)?.into_iter().map(|entry| entry.0).collect())
+    }
+
+    /// Claims a pending notification before it is sent. Returns the path of the claimed
+    /// file, or `None` if the notification was claimed or removed by someone else.
+    pub fn claim(&self, path: &Path) -> io::Result<Option<PathBuf>> {
+        let name = match path.file_stem().and_then(|name| name.to_str()) {
+            Some(name) => name,
+            None => return Ok(None),
+        };
+        let claimed = claimed_path(&self.dir, name);
+
+        // renaming is atomic, so only one of concurrent claims succeeds
+        match fs::rename(path, &claimed) {
+            Ok(()) => Ok(Some(claimed)),
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Releases a claimed notification that could not be sent, so that it is
+    /// pending again.
+    pub fn release(&self, claimed: &Path) -> io::Result<()> {
+        let name = match claimed_name(claimed) {
+            Some(name) => name,
+            None => return Ok(()),
+        };
+
+        fs::rename(claimed, self.dir.join(format!("{}.{}", name, EXTENSION)))
+    }
+
+    /// Removes a stored notification.
+    pub fn remove(&self, path: &Path) -> io::Result<()> {
+        match fs::remove_file(path) {
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+
+    /// Returns a unique file name, that starts with the creation time.
+    fn next_name(&self) -> String {
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+
+        format!(
+            "{:020}-{}-{}",
+            millis,
+            process::id(),
+            self.counter.fetch_add(1, Ordering::SeqCst)
+        )
+    }
+
+    /// Writes the json to the given path.
+    fn write(&self, json: &str, path: &Path) -> io::Result<PathBuf> {
+        fs::create_dir_all(&self.dir)?;
+
+        // write to a temporary file first, so that a partially written
+        // notification is never delivered
+        let mut tmp_path = path.to_owned();
+        tmp_path.set_extension("tmp");
+        fs::write(&tmp_path, json)?;
+        fs::rename(&tmp_path, path)?;
+
+        self.enforce_limits()?;
+        Ok(path.to_owned())
+    }
+
+    /// Releases the claimed notifications of processes that are not running anymore, e.g.
+    /// because they crashed while sending. If it is not known whether the process is
+    /// running, the notifications are released after the claim timeout.
+    fn release_expired_claims(&self) -> io::Result<()> {
+        let dir = match fs::read_dir(&self.dir) {
+            Ok(dir) => dir,
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err),
+        };
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        for entry in dir {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some(CLAIMED_EXTENSION) {
+                continue;
+            }
+
+            let expired = match claim_of(&path) {
+                Some((pid, _)) if pid != process::id() && !process_is_running(pid) => true,
+                Some((_, millis)) => {
+                    now.saturating_sub(Duration::from_millis(millis)) > CLAIM_TIMEOUT
+                }
+                None => false,
+            };
+            if expired {
+                match self.release(&path) {
+                    // released by someone else in the meantime
+                    Err(ref err) if err.kind() == io::ErrorKind::NotFound => {}
+                    result => result?,
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the path, the size and the modification time of all stored notifications
+    /// with the given extension, the oldest notification first.
+    fn entries(&self, extension: &str) -> io::Result<Vec<(PathBuf, u64, SystemTime)>> {
+        let dir = match fs::read_dir(&self.dir) {
+            Ok(dir) => dir,
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err),
+        };
+
+        let mut entries = Vec::new();
+        for entry in dir {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some(extension) {
+                continue;
+            }
+
+            // the file might have been delivered and removed in the meantime
+            if let Ok(metadata) = fs::metadata(&path) {
+                let modified = metadata.modified().unwrap_or_else(|_| SystemTime::now());
+                entries.push((path, metadata.len(), modified));
+            }
+        }
+
+        // the file names start with the creation time
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(entries)
+    }
+
+    /// Removes notifications that are too old and the oldest notifications, until the
+    /// outbox is within its limits. The claimed notifications count towards the limits,
+    /// but are not removed, because they are being sent.
+    fn enforce_limits(&self) -> io::Result<()> {
+        let now = SystemTime::now();
+        let mut entries = Vec::new();
+        let claimed = self.entries(CLAIMED_EXTENSION)?;
+        let mut stored = self.entries(EXTENSION)?;
+        stored.extend(self.entries(UNRESOLVED_EXTENSION)?);
+        stored.sort_by(|a, b| a.0.file_name().cmp(&b.0.file_name()));
+
+        for entry in stored {
+            let age = now.duration_since(entry.2).unwrap_or_default();
+            if age > self.max_age {
+                self.remove(&entry.0)?;
+            } else {
+                entries.push(entry);
+            }
+        }
+
+        let mut total_size: u64 = entries.iter().chain(&claimed).map(|entry| entry.1).sum();
+        let mut count = entries.len() + claimed.len();
+        for entry in entries {
+            if count <= self.max_files && total_size <= self.max_total_size {
+                break;
+            }
+
+            self.remove(&entry.0)?;
+            count -= 1;
+            total_size -= entry.1;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the path of a claimed notification, that contains the id of the process and the
+/// time of the claim.
+fn claimed_path(dir: &Path, name: &str) -> PathBuf {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+
+    dir.join(format!("{}.{}-{}.{}", name, process::id(), millis, CLAIMED_EXTENSION))
+}
+
+/// Returns the id of the process and the time of the claim of a claimed path.
+fn claim_of(claimed: &Path) -> Option<(u32, u64)> {
+    let claim = Path::new(claimed.file_stem()?).extension()?.to_str()?;
+    let mut parts = claim.splitn(2, '-');
+    let pid = parts.next()?.parse().ok()?;
+    let millis = parts.next()?.parse().ok()?;
+    Some((pid, millis))
+}
+
+/// Returns if the process with the given id is running. The processes are only known on
+/// Linux, on all other platforms every process is assumed to be running.
+fn process_is_running(pid: u32) -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        return Path::new("/proc").join(pid.to_string()).exists();
+    }
+
+    #[allow(unreachable_code)]
+    true
+}
+
+/// Returns the name of the notification of a claimed path.
+fn claimed_name(claimed: &Path) -> Option<&str> {
+    claimed
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| name.split('.').next())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Outbox;
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+    use std::process;
+    use std::thread;
+    use std::time::Duration;
+
+    /// Returns an empty directory for a test.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("bugsnag-outbox-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_store_and_remove() {
+        let outbox = Outbox::new(test_dir("store"));
+
+        let path = outbox.store("{}").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{}");
+        assert_eq!(outbox.pending().unwrap(), vec![path.clone()]);
+
+        outbox.remove(&path).unwrap();
+        assert!(outbox.pending().unwrap().is_empty());
+        let _ = fs::remove_dir_all(outbox.dir());
+    }
+
+    #[test]
+    fn test_claim_and_release() {
+        let outbox = Outbox::new(test_dir("claim"));
+
+        let path = outbox.store("{}").unwrap();
+        let claimed = outbox.claim(&path).unwrap().unwrap();
+        assert_eq!(fs::read_to_string(&claimed).unwrap(), "{}");
+        assert!(outbox.pending().unwrap().is_empty());
+        // a notification can only be claimed once
+        assert_eq!(outbox.claim(&path).unwrap(), None);
+
+        outbox.release(&claimed).unwrap();
+        assert_eq!(outbox.pending().unwrap(), vec![path]);
+
+        let claimed = outbox.store_claimed("{}").unwrap();
+        assert_eq!(outbox.pending().unwrap().len(), 1);
+        outbox.remove(&claimed).unwrap();
+        let _ = fs::remove_dir_all(outbox.dir());
+    }
+
+    #[test]
+    fn test_expired_claims_are_pending() {
+        let outbox = Outbox::new(test_dir("expired-claim"));
+        fs::create_dir_all(outbox.dir()).unwrap();
+        let claimed = format!("00000000000000000001-1-0.{}-1000.sending", process::id());
+        fs::write(outbox.dir().join(claimed), "{}").unwrap();
+
+        assert_eq!(
+            outbox.pending().unwrap(),
+            vec![outbox.dir().join("00000000000000000001-1-0.json")]
+        );
+        let _ = fs::remove_dir_all(outbox.dir());
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_claims_of_exited_processes_are_pending() {
+        let outbox = Outbox::new(test_dir("exited-claim"));
+        let claimed = outbox.store_claimed("{}").unwrap();
+        // larger than the maximum process id of Linux
+        let name = claimed
+            .file_name()
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .replace(&format!(".{}-", process::id()), ".4194305-");
+        fs::rename(&claimed, outbox.dir().join(name)).unwrap();
+
+        assert_eq!(outbox.pending().unwrap().len(), 1);
+        let _ = fs::remove_dir_all(outbox.dir());
+    }
+
+    #[test]
+    fn test_claims_of_running_processes_are_not_pending() {
+        let outbox = Outbox::new(test_dir("running-claim"));
+        outbox.store_claimed("{}").unwrap();
+
+        assert!(outbox.pending().unwrap().is_empty());
+        let _ = fs::remove_dir_all(outbox.dir());
+    }
+
+    #[test]
+    fn test_unresolved_notifications_are_not_pending() {
+        let outbox = Outbox::new(test_dir("unresolved")).max_files(2);
+
+        let first = outbox.store_unresolved("1").unwrap();
+        assert!(outbox.pending().unwrap().is_empty());
+
+        // the unresolved notifications count towards the limits
+        let second = outbox.store("2").unwrap();
+        let third = outbox.store_unresolved("3").unwrap();
+        assert!(!first.exists());
+        assert!(third.exists());
+        assert_eq!(outbox.pending().unwrap(), vec![second]);
+        let _ = fs::remove_dir_all(outbox.dir());
+    }
+
+    #[test]
+    fn test_pending_of_missing_dir() {
+        let outbox = Outbox::new(test_dir("missing"));
+
+        assert!(outbox.pending().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_max_files() {
+        let outbox = Outbox::new(test_dir("max-files")).max_files(2);
+
+        outbox.store("1").unwrap();
+        let second = outbox.store("2").unwrap();
+        let third = outbox.store("3").unwrap();
+
+        assert_eq!(outbox.pending().unwrap(), vec![second, third]);
+        let _ = fs::remove_dir_all(outbox.dir());
+    }
+
+    #[test]
+    fn test_limits_count_claimed_notifications() {
+        let outbox = Outbox::new(test_dir("max-claimed")).max_files(2);
+
+        let first = outbox.store("1").unwrap();
+        let claimed = outbox.store_claimed("2").unwrap();
+        let third = outbox.store("3").unwrap();
+
+        assert!(!first.exists());
+        assert!(claimed.exists());
+        assert_eq!(outbox.pending().unwrap(), vec![third]);
+        let _ = fs::remove_dir_all(outbox.dir());
+    }
+
+    #[test]
+    fn test_max_total_size() {
+        let outbox = Outbox::new(test_dir("max-size")).max_total_size(10);
+
+        outbox.store("12345").unwrap();
+        let second = outbox.store("12345").unwrap();
+        let third = outbox.store("1").unwrap();
+
+        assert_eq!(outbox.pending().unwrap(), vec![second, third]);
+        let _ = fs::remove_dir_all(outbox.dir());
+    }
+
+    #[test]
+    fn test_max_age() {
+        let outbox = Outbox::new(test_dir("max-age")).max_age(Duration::from_millis(50));
+
+        outbox.store("{}").unwrap();
+        thread::sleep(Duration::from_millis(100));
+
+        assert!(outbox.pending().unwrap().is_empty());
+        let _ = fs::remove_dir_all(outbox.dir());
+    }
+}
@@ -1,12 +1,12 @@
-use super::{Bugsnag, Error, Severity};
+use super::{Bugsnag, Error, NotifyOutcome, Severity, SeverityReason};
 
-use std::panic::PanicInfo;
+use std::panic::PanicHookInfo;
 
 pub fn handle(
     api: &Bugsnag,
-    info: &PanicInfo,
+    info: &PanicHookInfo,
     methods_to_ignore: Option<&[&str]>,
-) -> Result<(), Error> {
+) -> Result<NotifyOutcome, Error> {
     let message = if let Some(data) = info.payload().downcast_ref::<String>() {
         data.to_owned()
     } else if let Some(data) = info.payload().downcast_ref::<&str>() {
@@ -15,14 +15,17 @@ pub fn handle(
         format!("Error: {:?}", info.payload())
     };
 
+    // the process might be about to die, so the notification is not queued
     let notify = api.notify("Panic", message.as_str())
-        .severity(Severity::Error);
+        .severity(Severity::Error)
+        .severity_reason(SeverityReason::UnhandledPanic)
+        .synchronous();
 
-    let result = if let Some(methods_to_ignore) = methods_to_ignore {
+    let mut notify = if let Some(methods_to_ignore) = methods_to_ignore {
         notify.methods_to_ignore(methods_to_ignore)
     } else {
         notify
-    }.send();
+    };
 
-    result
+    notify.send()
 }
@@ -0,0 +1,167 @@
+//! Module for converting the absolute paths of the build machine into stable relative paths.
+
+use regex::Regex;
+use std::cmp::Reverse;
+
+/// Patterns for the directories of sources that are not part of the project, the matched
+/// part is removed from a path.
+const DEFAULT_PATTERNS: &[&str] = &[
+    // the sources of crates.io dependencies, e.g. in `$CARGO_HOME/registry/src/<index>/`
+    r"^.*/registry/src/[^/]+/",
+    // the sources of git dependencies, e.g. in `$CARGO_HOME/git/checkouts/`
+    r"^.*/git/checkouts/",
+    // the sources of the standard library in the sysroot of the compiler
+    r"^/rustc/[0-9a-f]+/",
+    r"^.*/lib/rustlib/src/rust/",
+];
+
+/// Converts the absolute paths of the files in a stacktrace into relative paths, that do
+/// not depend on the machine the application was build on. This allows Bugsnag to link
+/// the frames to the code and to group the same error from different build machines.
+///
+/// By default, the following prefixes are removed:
+///
+/// * `$CARGO_HOME/registry/src/<index>/`, so that `serde-1.0.0/src/de.rs` remains
+/// * `$CARGO_HOME/git/checkouts/`
+/// * `/rustc/<hash>/` and the sysroot of the compiler, so that `library/std/src/...` remains
+///
+/// Additional prefixes, like the project root, can be added. Paths that do not start with
+/// any prefix are not changed.
+#[derive(Debug, Clone)]
+pub struct PathNormalizer {
+    prefixes: Vec<String>,
+    patterns: Vec<Regex>,
+}
+
+impl PathNormalizer {
+    pub fn new() -> PathNormalizer {
+        PathNormalizer {
+            prefixes: Vec::new(),
+            patterns: DEFAULT_PATTERNS
+                .iter()
+                .map(|p| Regex::new(p).expect("the default patterns are valid regexes"))
+                .collect(),
+        }
+    }
+
+    /// Adds a prefix that is removed from the paths, e.g. the project root.
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        let prefix = prefix.trim_end_matches('/');
+        if !prefix.is_empty() {
+            self.prefixes.push(prefix.to_owned());
+            // the longest prefix is removed, if multiple prefixes match
+            self.prefixes.sort_by_key(|prefix| Reverse(prefix.len()));
+        }
+        self
+    }
+
+    /// Returns the normalized path.
+    pub fn normalize(&self, path: &str) -> String {
+        for prefix in &self.prefixes {
+            if let Some(rest) = strip_dir(path, prefix) {
+                return rest.to_owned();
+            }
+        }
+
+        for pattern in &self.patterns {
+            if let Some(found) = pattern.find(path) {
+                return path[found.end()..].to_owned();
+            }
+        }
+
+        path.to_owned()
+    }
+}
+
+/// Returns the path relative to the given directory, if the path is in the directory.
+/// Unlike `str::starts_with`, the directory `/project/app` does not contain the path
+/// `/project/app2/src/main.rs`.
+pub fn strip_dir<'a>(path: &'a str, dir: &str) -> Option<&'a str> {
+    let dir = dir.trim_end_matches('/');
+
+    if path.starts_with(dir) && path[dir.len()..].starts_with('/') {
+        Some(&path[dir.len() + 1..])
+    } else {
+        None
+    }
+}
+
+impl Default for PathNormalizer {
+    fn default() -> PathNormalizer {
+        PathNormalizer::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PathNormalizer;
+
+    #[test]
+    fn test_project_root() {
+        let normalizer = PathNormalizer::new().prefix("/home/ci/build/1234/");
+
+        assert_eq!(normalizer.normalize("/home/ci/build/1234/src/main.rs"), "src/main.rs");
+        assert_eq!(
+            normalizer.normalize("/home/ci/build/12345/src/main.rs"),
+            "/home/ci/build/12345/src/main.rs"
+        );
+    }
+
+    #[test]
+    fn test_longest_prefix() {
+        let normalizer = PathNormalizer::new()
+            .prefix("/project")
+            .prefix("/project/app");
+
+        assert_eq!(normalizer.normalize("/project/app/src/main.rs"), "src/main.rs");
+        assert_eq!(normalizer.normalize("/project/lib/src/lib.rs"), "lib/src/lib.rs");
+    }
+
+    #[test]
+    fn test_cargo_registry() {
+        let normalizer = PathNormalizer::new();
+
+        assert_eq!(
+            normalizer.normalize(
+                "/home/ci/.cargo/registry/src/index.crates.io-6f17d22bba15001f/serde-1.0.0/src/de.rs"
+            ),
+            "serde-1.0.0/src/de.rs"
+        );
+        assert_eq!(
+            normalizer
+                .normalize("/opt/cargo/registry/src/github.com-1ecc6299db9ec823/log-0.3.9/src/lib.rs"),
+            "log-0.3.9/src/lib.rs"
+        );
+        assert_eq!(
+            normalizer
+                .normalize("/home/ci/.cargo/git/checkouts/hyper-0123456789abcdef/a1b2c3d/src/lib.rs"),
+            "hyper-0123456789abcdef/a1b2c3d/src/lib.rs"
+        );
+    }
+
+    #[test]
+    fn test_rustc_sysroot() {
+        let normalizer = PathNormalizer::new();
+
+        assert_eq!(
+            normalizer.normalize(
+                "/rustc/90b35a6239c3d8bdabc530a6a0816f7ff89a0aaf/library/std/src/panicking.rs"
+            ),
+            "library/std/src/panicking.rs"
+        );
+        assert_eq!(
+            normalizer.normalize(
+                "/home/ci/.rustup/toolchains/stable-x86_64-unknown-linux-gnu/lib/rustlib/src/rust/library/core/src/option.rs"
+            ),
+            "library/core/src/option.rs"
+        );
+    }
+
+    #[test]
+    fn test_unknown_path() {
+        let normalizer = PathNormalizer::new().prefix("");
+
+        assert_eq!(normalizer.normalize("/usr/src/main.rs"), "/usr/src/main.rs");
+        assert_eq!(normalizer.normalize(""), "");
+    }
+}
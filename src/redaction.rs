@@ -0,0 +1,170 @@
+//! Module for removing sensitive values, like passwords, from the notifications.
+
+use regex::Regex;
+use serde_json::Value;
+
+/// The keys that are redacted by default.
+const DEFAULT_KEYS: &[&str] = &[
+    "password",
+    "authorization",
+    "cookie",
+    "set-cookie",
+    "token",
+    "access_token",
+    "refresh_token",
+    "secret",
+    "client_secret",
+];
+
+/// The value that replaces the values of the redacted keys.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// The keys whose values are replaced with `[REDACTED]`, before a notification leaves the
+/// process. The keys are redacted in every object of the events, e.g. in the metadata, the
+/// metadata of the breadcrumbs and the request headers that are added as metadata.
+///
+/// A string matches the key that is equal to it, ignoring the case, so `token` matches
+/// `Token`, but not `tokens_used`. A regex matches every key it finds a match in, e.g. to
+/// redact all keys that contain a string.
+///
+/// # Example
+///
+/// ```
+/// extern crate bugsnag;
+/// extern crate regex;
+///
+/// let keys = bugsnag::RedactedKeys::new()
+///     .key("api_key")
+///     .pattern(regex::Regex::new("(?i)-token$").unwrap());
+///
+/// assert!(keys.is_redacted("Authorization"));
+/// assert!(keys.is_redacted("API_KEY"));
+/// assert!(keys.is_redacted("X-Auth-Token"));
+/// assert!(!keys.is_redacted("tokens_used"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct RedactedKeys {
+    keys: Vec<String>,
+    patterns: Vec<Regex>,
+}
+
+impl RedactedKeys {
+    /// Creates the default keys: `password`, `authorization`, `cookie`, `set-cookie`,
+    /// `token`, `access_token`, `refresh_token`, `secret` and `client_secret`.
+    pub fn new() -> RedactedKeys {
+        DEFAULT_KEYS
+            .iter()
+            .fold(RedactedKeys::none(), |keys, key| keys.key(key))
+    }
+
+    /// Creates an empty list, so no key is redacted.
+    pub fn none() -> RedactedKeys {
+        RedactedKeys {
+            keys: Vec::new(),
+            patterns: Vec::new(),
+        }
+    }
+
+    /// Adds a key that is redacted, ignoring the case.
+    pub fn key(mut self, key: &str) -> Self {
+        self.keys.push(key.to_lowercase());
+        self
+    }
+
+    /// Adds a regex for the keys that are redacted.
+    pub fn pattern(mut self, pattern: Regex) -> Self {
+        self.patterns.push(pattern);
+        self
+    }
+
+    /// Returns if the value of the given key is redacted.
+    pub fn is_redacted(&self, key: &str) -> bool {
+        self.keys.contains(&key.to_lowercase())
+            || self.patterns.iter().any(|pattern| pattern.is_match(key))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty() && self.patterns.is_empty()
+    }
+
+    /// Replaces the values of the redacted keys in all objects of the given json.
+    pub fn redact(&self, value: &mut Value) {
+        match *value {
+            Value::Object(ref mut map) => for (key, value) in map.iter_mut() {
+                if self.is_redacted(key) {
+                    *value = Value::String(REDACTED.to_owned());
+                } else {
+                    self.redact(value);
+                }
+            },
+            Value::Array(ref mut values) => for value in values {
+                self.redact(value);
+            },
+            _ => {}
+        }
+    }
+}
+
+impl Default for RedactedKeys {
+    fn default() -> RedactedKeys {
+        RedactedKeys::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RedactedKeys;
+    use regex::Regex;
+    use serde_json::{json, Value};
+
+    #[test]
+    fn test_default_keys() {
+        let keys = RedactedKeys::new();
+
+        assert!(keys.is_redacted("password"));
+        assert!(keys.is_redacted("Authorization"));
+        assert!(keys.is_redacted("Set-Cookie"));
+        assert!(keys.is_redacted("access_token"));
+        assert!(keys.is_redacted("CLIENT_SECRET"));
+        assert!(!keys.is_redacted("user"));
+        assert!(!keys.is_redacted("tokens_used"));
+        assert!(!keys.is_redacted("secretary"));
+        assert!(!keys.is_redacted("passwordless_login"));
+        assert!(!RedactedKeys::none().is_redacted("password"));
+    }
+
+    #[test]
+    fn test_patterns() {
+        let keys = RedactedKeys::none().pattern(Regex::new("^(?i)x-api-key$").unwrap());
+
+        assert!(keys.is_redacted("X-Api-Key"));
+        assert!(!keys.is_redacted("x-api-key-id"));
+        assert!(!keys.is_redacted("password"));
+    }
+
+    #[test]
+    fn test_redact_nested_values() {
+        let mut json = json!({
+            "request": {
+                "url": "/login",
+                "headers": {"Authorization": "Bearer abc", "Accept": "text/html"},
+                "params": [{"password": "hunter2"}, {"name": "me"}]
+            },
+            "token": {"nested": "replaced as a whole"},
+            "count": 1
+        });
+
+        RedactedKeys::new().redact(&mut json);
+
+        let expected: Value = json!({
+            "request": {
+                "url": "/login",
+                "headers": {"Authorization": "[REDACTED]", "Accept": "text/html"},
+                "params": [{"password": "[REDACTED]"}, {"name": "me"}]
+            },
+            "token": "[REDACTED]",
+            "count": 1
+        });
+        assert_eq!(json, expected);
+    }
+}
@@ -0,0 +1,299 @@
+//! Module for the error report, that owns all the data of a notification.
+
+use serde::Serialize;
+use serde_json::{self, Value};
+
+use super::{Error, Severity, SeverityReason};
+use super::appinfo::AppInfo;
+use super::breadcrumbs::Breadcrumb;
+use super::deviceinfo::DeviceInfo;
+use super::event::Event;
+use super::exception::Exception;
+use super::metadata::MetaData;
+use super::notification::Notification;
+use super::redaction::RedactedKeys;
+use super::session::Session;
+use super::stacktrace::Frame;
+use super::threads::Thread;
+use super::user::User;
+
+/// An error report with everything that is send to Bugsnag.
+///
+/// Unlike the `NotifyBuilder`, the report owns all its data, so it can be created on one
+/// thread, stored or send to another thread and converted into json later. Use
+/// `NotifyBuilder::into_report` to create a report and `Bugsnag::send_report` to send it.
+#[derive(Debug, Clone)]
+pub struct Report {
+    api_key: String,
+    /// The error class and message of the error, followed by the ones of its causes.
+    errors: Vec<(String, String)>,
+    stacktrace: Vec<Frame>,
+    severity: Option<Severity>,
+    severity_reason: SeverityReason,
+    context: Option<String>,
+    grouping_hash: Option<String>,
+    device_info: DeviceInfo,
+    app_info: Option<AppInfo>,
+    metadata: MetaData,
+    user: Option<User>,
+    breadcrumbs: Vec<Breadcrumb>,
+    threads: Vec<Thread>,
+    session: Option<Session>,
+    redacted_keys: RedactedKeys,
+}
+
+impl Report {
+    pub(crate) fn new(
+        api_key: &str,
+        errors: Vec<(String, String)>,
+        stacktrace: Vec<Frame>,
+        device_info: DeviceInfo,
+        app_info: Option<AppInfo>,
+    ) -> Report {
+        Report {
+            api_key: api_key.to_owned(),
+            errors,
+            stacktrace,
+            severity: None,
+            severity_reason: SeverityReason::HandledException,
+            context: None,
+            grouping_hash: None,
+            device_info,
+            app_info,
+            metadata: MetaData::new(),
+            user: None,
+            breadcrumbs: Vec::new(),
+            threads: Vec::new(),
+            session: None,
+            redacted_keys: RedactedKeys::new(),
+        }
+    }
+
+    /// Returns the error class and the message of the reported error, followed by the
+    /// ones of its causes.
+    pub fn errors(&self) -> &[(String, String)] {
+        &self.errors
+    }
+
+    /// Returns the error classes and the messages, e.g. to rewrite the messages.
+    pub fn errors_mut(&mut self) -> &mut [(String, String)] {
+        &mut self.errors
+    }
+
+    pub fn severity(&self) -> Option<&Severity> {
+        self.severity.as_ref()
+    }
+
+    pub fn severity_reason(&self) -> &SeverityReason {
+        &self.severity_reason
+    }
+
+    pub fn context(&self) -> Option<&str> {
+        self.context.as_deref()
+    }
+
+    pub fn grouping_hash(&self) -> Option<&str> {
+        self.grouping_hash.as_deref()
+    }
+
+    /// Returns the values of the given metadata tab.
+    pub fn metadata(&self, tab: &str) -> Option<&Value> {
+        self.metadata.tab(tab)
+    }
+
+    pub fn user(&self) -> Option<&User> {
+        self.user.as_ref()
+    }
+
+    pub fn set_severity(&mut self, severity: Option<Severity>) {
+        self.severity = severity;
+    }
+
+    pub(crate) fn set_severity_reason(&mut self, severity_reason: SeverityReason) {
+        self.severity_reason = severity_reason;
+    }
+
+    pub fn set_context(&mut self, context: Option<String>) {
+        self.context = context;
+    }
+
+    pub fn set_grouping_hash(&mut self, grouping_hash: Option<String>) {
+        self.grouping_hash = grouping_hash;
+    }
+
+    /// Converts the given value to json and stores it in the given metadata tab.
+    /// An existing tab with the same name is replaced.
+    pub fn set_metadata<T: Serialize + ?Sized>(
+        &mut self,
+        tab: &str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.metadata.set_tab(tab, value)
+    }
+
+    /// Removes the given metadata tab.
+    pub fn remove_metadata(&mut self, tab: &str) {
+        self.metadata.remove_tab(tab);
+    }
+
+    pub(crate) fn replace_metadata(&mut self, metadata: MetaData) {
+        self.metadata = metadata;
+    }
+
+    pub fn set_user(&mut self, user: Option<User>) {
+        self.user = user;
+    }
+
+    pub(crate) fn set_breadcrumbs(&mut self, breadcrumbs: Vec<Breadcrumb>) {
+        self.breadcrumbs = breadcrumbs;
+    }
+
+    pub(crate) fn set_threads(&mut self, threads: Vec<Thread>) {
+        self.threads = threads;
+    }
+
+    pub(crate) fn set_session(&mut self, session: Option<Session>) {
+        self.session = session;
+    }
+
+    pub(crate) fn set_redacted_keys(&mut self, redacted_keys: RedactedKeys) {
+        self.redacted_keys = redacted_keys;
+    }
+
+    /// Converts the report into the json of a notification. The values of the redacted keys
+    /// are replaced in all objects of the event.
+    pub fn to_json(&self) -> Result<String, Error> {
+        let exceptions: Vec<_> = self.errors
+            .iter()
+            .enumerate()
+            .map(|(index, (error_class, message))| {
+                // the stacktrace is only known for the reported error, not for its causes
+                let frames = if index == 0 { &self.stacktrace[..] } else { &[] };
+                Exception::new(error_class, message, frames)
+            })
+            .collect();
+        let mut event = Event::new(
+            &exceptions,
+            self.severity.as_ref(),
+            self.context.as_deref(),
+            self.grouping_hash.as_deref(),
+            &self.device_info,
+            &self.app_info,
+        );
+        if !self.metadata.is_empty() {
+            event.set_metadata(&self.metadata);
+        }
+        if let Some(ref user) = self.user {
+            event.set_user(user);
+        }
+        if !self.breadcrumbs.is_empty() {
+            event.set_breadcrumbs(&self.breadcrumbs);
+        }
+        event.set_severity_reason(&self.severity_reason);
+        if !self.threads.is_empty() {
+            event.set_threads(&self.threads);
+        }
+        if let Some(ref session) = self.session {
+            event.set_session(session);
+        }
+        let events = vec![event];
+        let notification = Notification::new(&self.api_key, &events);
+
+        if self.redacted_keys.is_empty() {
+            return serde_json::to_string(&notification).map_err(|_| Error::JsonConversionFailed);
+        }
+
+        let mut json =
+            serde_json::to_value(&notification).map_err(|_| Error::JsonConversionFailed)?;
+        // the api key of the notification is not redacted
+        if let Some(events) = json.get_mut("events") {
+            self.redacted_keys.redact(events);
+        }
+        serde_json::to_string(&json).map_err(|_| Error::JsonConversionFailed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Report;
+    use super::super::{Severity, SeverityReason};
+    use super::super::deviceinfo::DeviceInfo;
+    use super::super::redaction::RedactedKeys;
+    use super::super::stacktrace::Frame;
+    use serde_json::{self, json, Value};
+    use std::thread;
+
+    fn report() -> Report {
+        Report::new(
+            "api-key",
+            vec![
+                ("Outer".to_owned(), "outer message".to_owned()),
+                ("Inner".to_owned(), "inner message".to_owned()),
+            ],
+            vec![Frame::new("src/main.rs", 10, "my_app::main", true)],
+            DeviceInfo::new("1.0.0", "testmachine"),
+            None,
+        )
+    }
+
+    #[test]
+    fn test_report_is_send_and_static() {
+        fn assert_send<T: Send + 'static>() {}
+        assert_send::<Report>();
+    }
+
+    #[test]
+    fn test_report_to_json() {
+        let mut report = report();
+        report.set_severity(Some(Severity::Warning));
+        report.set_context(Some("test/context".to_owned()));
+
+        let json: Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert_eq!(json["apiKey"], "api-key");
+        let event = &json["events"][0];
+        assert_eq!(event["severity"], "warning");
+        assert_eq!(event["severityReason"]["type"], "handledException");
+        assert_eq!(event["unhandled"], false);
+        assert_eq!(event["context"], "test/context");
+        assert_eq!(event["exceptions"][0]["errorClass"], "Outer");
+        assert_eq!(event["exceptions"][0]["stacktrace"][0]["method"], "my_app::main");
+        assert_eq!(event["exceptions"][1]["errorClass"], "Inner");
+        assert_eq!(event["exceptions"][1]["stacktrace"], json!([]));
+        assert!(event.get("metaData").is_none());
+    }
+
+    #[test]
+    fn test_report_to_json_redacts_keys() {
+        let mut report = report();
+        report
+            .set_metadata(
+                "request",
+                &json!({"headers": {"Cookie": "session=abc", "Accept": "text/html"}}),
+            )
+            .unwrap();
+        report.set_metadata("login", &json!({"password": "hunter2"})).unwrap();
+
+        let json: Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        let metadata = &json["events"][0]["metaData"];
+        assert_eq!(metadata["request"]["headers"]["Cookie"], "[REDACTED]");
+        assert_eq!(metadata["request"]["headers"]["Accept"], "text/html");
+        assert_eq!(metadata["login"]["password"], "[REDACTED]");
+        assert_eq!(json["apiKey"], "api-key");
+
+        report.set_redacted_keys(RedactedKeys::none());
+        let json: Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert_eq!(json["events"][0]["metaData"]["login"]["password"], "hunter2");
+    }
+
+    #[test]
+    fn test_report_to_json_on_other_thread() {
+        let mut report = report();
+        report.set_severity_reason(SeverityReason::UnhandledPanic);
+        let expected = report.to_json().unwrap();
+
+        let json = thread::spawn(move || report.to_json().unwrap())
+            .join()
+            .unwrap();
+        assert_eq!(json, expected);
+    }
+}
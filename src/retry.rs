@@ -0,0 +1,177 @@
+//! Module for retrying deliveries that failed because of transient problems.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+use super::Error;
+
+/// Defines how often and how long to wait before a failed delivery is retried.
+///
+/// A delivery is retried on network errors, if Bugsnag is rate limiting (status 429)
+/// and on server errors (status 5xx). Deliveries that were rejected by Bugsnag, because
+/// of the json or the api key, are never retried.
+///
+/// The delay doubles with every attempt, starting with the base delay and never
+/// exceeding the maximum delay. If Bugsnag requested a delay with the `Retry-After`
+/// header, at least this delay is used. If the requested delay is longer than the maximum
+/// delay, the delivery is not retried.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+    jitter: bool,
+}
+
+impl RetryPolicy {
+    /// Creates a policy that tries to deliver a notification up to `max_attempts` times.
+    pub fn new(max_attempts: u32, base_delay: Duration) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: max_attempts.max(1),
+            base_delay,
+            max_delay: Duration::from_secs(60),
+            jitter: true,
+        }
+    }
+
+    /// Creates a policy that never retries a delivery.
+    pub fn none() -> RetryPolicy {
+        RetryPolicy::new(1, Duration::from_secs(0))
+    }
+
+    /// Sets the maximum delay between two attempts. The default is 60 seconds.
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Enables or disables the randomization of the delays, which prevents many
+    /// clients from retrying at the same time. Enabled by default.
+    pub fn jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// Returns if a delivery that failed with the given error should be retried.
+    pub fn is_retryable(error: &Error) -> bool {
+        matches!(
+            *error,
+            Error::JsonTransferFailed | Error::RateLimited | Error::ServerError(_)
+        )
+    }
+
+    /// Returns the delay before the next attempt, after `attempt` attempts failed, or `None`
+    /// if the delay requested by Bugsnag exceeds the maximum delay.
+    pub fn delay(&self, attempt: u32, retry_after: Option<Duration>) -> Option<Duration> {
+        let factor = 1u32.checked_shl(attempt.saturating_sub(1)).unwrap_or(u32::MAX);
+        let mut delay = self.base_delay
+            .checked_mul(factor)
+            .map_or(self.max_delay, |delay| delay.min(self.max_delay));
+
+        if self.jitter {
+            // a random delay between the half and the full delay
+            let half = delay / 2;
+            let nanos = (half.as_nanos() as u64).max(1);
+            delay = half + Duration::from_nanos(random() % nanos);
+        }
+
+        match retry_after {
+            // waiting blocks the sending thread, e.g. the panic handler
+            Some(retry_after) if retry_after > self.max_delay => None,
+            Some(retry_after) if retry_after > delay => Some(retry_after),
+            _ => Some(delay),
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    /// By default, deliveries are not retried.
+    fn default() -> RetryPolicy {
+        RetryPolicy::none()
+    }
+}
+
+/// Returns a random number. The standard library seeds every `RandomState` randomly,
+/// which is good enough for the jitter.
+fn random() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RetryPolicy;
+    use super::super::Error;
+    use std::time::Duration;
+
+    #[test]
+    fn test_exponential_delay() {
+        let policy = RetryPolicy::new(5, Duration::from_millis(100)).jitter(false);
+
+        assert_eq!(policy.delay(1, None), Some(Duration::from_millis(100)));
+        assert_eq!(policy.delay(2, None), Some(Duration::from_millis(200)));
+        assert_eq!(policy.delay(3, None), Some(Duration::from_millis(400)));
+    }
+
+    #[test]
+    fn test_max_delay() {
+        let policy = RetryPolicy::new(100, Duration::from_secs(1))
+            .max_delay(Duration::from_secs(10))
+            .jitter(false);
+
+        assert_eq!(policy.delay(5, None), Some(Duration::from_secs(10)));
+        assert_eq!(policy.delay(64, None), Some(Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn test_jitter() {
+        let policy = RetryPolicy::new(5, Duration::from_millis(100));
+
+        for _ in 0..100 {
+            let delay = policy.delay(2, None).unwrap();
+            assert!(delay >= Duration::from_millis(100));
+            assert!(delay <= Duration::from_millis(200));
+        }
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let policy = RetryPolicy::new(5, Duration::from_millis(100)).jitter(false);
+
+        assert_eq!(
+            policy.delay(1, Some(Duration::from_secs(3))),
+            Some(Duration::from_secs(3))
+        );
+        assert_eq!(
+            policy.delay(2, Some(Duration::from_millis(1))),
+            Some(Duration::from_millis(200))
+        );
+    }
+
+    #[test]
+    fn test_retry_after_exceeds_max_delay() {
+        let policy = RetryPolicy::new(5, Duration::from_millis(100))
+            .max_delay(Duration::from_secs(10))
+            .jitter(false);
+
+        assert_eq!(
+            policy.delay(1, Some(Duration::from_secs(10))),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(policy.delay(1, Some(Duration::from_secs(3600))), None);
+    }
+
+    #[test]
+    fn test_is_retryable() {
+        assert!(RetryPolicy::is_retryable(&Error::JsonTransferFailed));
+        assert!(RetryPolicy::is_retryable(&Error::RateLimited));
+        assert!(RetryPolicy::is_retryable(&Error::ServerError(503)));
+        assert!(!RetryPolicy::is_retryable(&Error::BadRequest));
+        assert!(!RetryPolicy::is_retryable(&Error::Unauthorized));
+        assert!(!RetryPolicy::is_retryable(&Error::PayloadTooLarge));
+    }
+}
@@ -0,0 +1,376 @@
+//! Module for tracking sessions, which Bugsnag uses to calculate the stability of an
+//! application.
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::mem;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+use serde_json;
+use uuid::Uuid;
+
+use super::appinfo::AppInfo;
+use super::delivery::Delivery;
+use super::deviceinfo::DeviceInfo;
+use super::notification::Notifier;
+use super::retry::RetryPolicy;
+use super::{timestamp, Error};
+
+pub const PAYLOAD_VERSION: &str = "1.0";
+
+/// The interval in which the session counts are send to Bugsnag.
+pub const FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The id of the next session tracker.
+static NEXT_TRACKER_ID: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    /// The current session of the thread, by the id of its tracker.
+    static THREAD_SESSIONS: RefCell<HashMap<usize, ThreadSession>> =
+        RefCell::new(HashMap::new());
+}
+
+/// The session an event belongs to, with the number of events in this session.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Session {
+    id: String,
+    started_at: String,
+    events: SessionEvents,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct SessionEvents {
+    handled: u64,
+    unhandled: u64,
+}
+
+/// The number of sessions that were started in a minute.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SessionCount {
+    started_at: String,
+    sessions_started: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SessionPayload<'a> {
+    notifier: Notifier,
+    #[serde(skip_serializing_if = "Option::is_none")] app: &'a Option<AppInfo>,
+    device: &'a DeviceInfo,
+    session_counts: &'a [SessionCount],
+}
+
+/// The current session of a thread.
+#[derive(Default)]
+struct ThreadSession {
+    current: Option<Session>,
+    paused: bool,
+}
+
+/// Tracks the current session of every thread and counts the started sessions of all
+/// threads per minute.
+///
+/// Every thread has its own current session, e.g. for the request it handles, so that the
+/// events of a thread only count towards the session of this thread.
+pub struct SessionTracker {
+    id: usize,
+    /// The number of started sessions per minute.
+    counts: Mutex<BTreeMap<String, u64>>,
+}
+
+impl SessionTracker {
+    pub fn new() -> SessionTracker {
+        SessionTracker {
+            id: NEXT_TRACKER_ID.fetch_add(1, Ordering::SeqCst),
+            counts: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Starts a new session, which replaces the current session of the calling thread.
+    pub fn start(&self) {
+        let started_at = timestamp::now();
+        *self.lock().entry(minute_of(&started_at)).or_insert(0) += 1;
+
+        self.with_thread_session(|thread| {
+            thread.current = Some(Session {
+                id: Uuid::new_v4().to_string(),
+                started_at,
+                events: SessionEvents {
+                    handled: 0,
+                    unhandled: 0,
+                },
+            });
+            thread.paused = false;
+        });
+    }
+
+    /// Pauses the current session of the calling thread, events are not part of a session
+    /// until it is resumed.
+    pub fn pause(&self) {
+        self.with_thread_session(|thread| thread.paused = true);
+    }
+
+    /// Resumes the paused session of the calling thread or starts a new session, if there
+    /// is none.
+    pub fn resume(&self) {
+        let resumed = self.with_thread_session(|thread| {
+            thread.paused = false;
+            thread.current.is_some()
+        });
+
+        if !resumed {
+            self.start();
+        }
+    }
+
+    /// Counts an event in the current session of the calling thread and returns the session
+    /// including this event. Returns `None` if there is no active session.
+    pub fn track_event(&self, unhandled: bool) -> Option<Session> {
+        self.with_thread_session(|thread| {
+            if thread.paused {
+                return None;
+            }
+
+            let session = thread.current.as_mut()?;
+            if unhandled {
+                session.events.unhandled += 1;
+            } else {
+                session.events.handled += 1;
+            }
+            Some(session.clone())
+        })
+    }
+
+    /// Sends the session counts that were not send yet to Bugsnag. If the delivery failed
+    /// because of a transient problem, the counts are kept for the next delivery.
+    pub fn send(
+        &self,
+        delivery: &Delivery,
+        app: &Option<AppInfo>,
+        device: &DeviceInfo,
+    ) -> Result<(), Error> {
+        let counts = mem::take(&mut *self.lock());
+        if counts.is_empty() {
+            return Ok(());
+        }
+
+        let session_counts: Vec<_> = counts
+            .iter()
+            .map(|(started_at, sessions_started)| SessionCount {
+                started_at: started_at.clone(),
+                sessions_started: *sessions_started,
+            })
+            .collect();
+        let payload = SessionPayload {
+            notifier: Notifier::default(),
+            app,
+            device,
+            session_counts: &session_counts,
+        };
+
+        let result = match serde_json::to_string(&payload) {
+            Ok(json) => delivery.send(&json),
+            Err(_) => Err(Error::JsonConversionFailed),
+        };
+
+        if let Err(ref err) = result {
+            if RetryPolicy::is_retryable(err) {
+                let mut state = self.lock();
+                for (started_at, sessions_started) in counts {
+                    *state.entry(started_at).or_insert(0) += sessions_started;
+                }
+            }
+        }
+
+        result
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<String, u64>> {
+        self.counts.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Calls `f` with the session of the calling thread.
+    fn with_thread_session<F, T>(&self, f: F) -> T
+    where
+        F: FnOnce(&mut ThreadSession) -> T,
+    {
+        THREAD_SESSIONS.with(|sessions| f(sessions.borrow_mut().entry(self.id).or_default()))
+    }
+}
+
+impl Default for SessionTracker {
+    fn default() -> SessionTracker {
+        SessionTracker::new()
+    }
+}
+
+impl Drop for SessionTracker {
+    fn drop(&mut self) {
+        // the sessions of the other threads are removed when the threads exit
+        let _ = THREAD_SESSIONS.try_with(|sessions| sessions.borrow_mut().remove(&self.id));
+    }
+}
+
+/// Truncates a timestamp to the start of its minute.
+fn minute_of(timestamp: &str) -> String {
+    // the timestamps have the format 2018-01-31T14:25:03.123Z
+    format!("{}:00.000Z", timestamp.get(..16).unwrap_or(timestamp))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{minute_of, SessionTracker};
+    use super::super::Error;
+    use super::super::delivery::Delivery;
+    use super::super::deviceinfo::DeviceInfo;
+    use super::super::retry::RetryPolicy;
+    use super::super::transport::RecordingTransport;
+    use serde_json::{self, Value};
+    use std::sync::Arc;
+    use std::thread;
+
+    fn delivery(status: u16) -> (Delivery, ::std::sync::Arc<RecordingTransport>) {
+        let transport = RecordingTransport::new(status);
+        let delivery = Delivery {
+            transport: transport.clone(),
+            retry_policy: RetryPolicy::none(),
+            url: "https://sessions.example.com".to_owned(),
+            api_key: "api-key".to_owned(),
+            payload_version: super::PAYLOAD_VERSION.to_owned(),
+            outbox: None,
+        };
+        (delivery, transport)
+    }
+
+    #[test]
+    fn test_minute_of() {
+        assert_eq!(minute_of("2018-01-31T14:25:03.123Z"), "2018-01-31T14:25:00.000Z");
+    }
+
+    #[test]
+    fn test_track_event_without_session() {
+        let tracker = SessionTracker::new();
+
+        assert_eq!(tracker.track_event(false), None);
+    }
+
+    #[test]
+    fn test_track_events() {
+        let tracker = SessionTracker::new();
+        tracker.start();
+
+        tracker.track_event(false);
+        tracker.track_event(true);
+        let session = tracker.track_event(false).unwrap();
+
+        assert_eq!(session.events.handled, 2);
+        assert_eq!(session.events.unhandled, 1);
+        assert_eq!(session.id.len(), 36);
+    }
+
+    #[test]
+    fn test_pause_and_resume() {
+        let tracker = SessionTracker::new();
+        tracker.start();
+        let id = tracker.track_event(false).unwrap().id;
+
+        tracker.pause();
+        assert_eq!(tracker.track_event(false), None);
+
+        tracker.resume();
+        let session = tracker.track_event(false).unwrap();
+        assert_eq!(session.id, id);
+        assert_eq!(session.events.handled, 2);
+    }
+
+    #[test]
+    fn test_resume_starts_session() {
+        let tracker = SessionTracker::new();
+        tracker.resume();
+
+        assert!(tracker.track_event(false).is_some());
+    }
+
+    #[test]
+    fn test_sessions_of_threads() {
+        let tracker = Arc::new(SessionTracker::new());
+        tracker.start();
+        let id = tracker.track_event(false).unwrap().id;
+
+        let other = tracker.clone();
+        let other_thread = thread::spawn(move || {
+            // the session of another thread is not the current session
+            assert_eq!(other.track_event(false), None);
+            other.start();
+            other.track_event(true).unwrap()
+        });
+        let other_session = other_thread.join().unwrap();
+        assert_ne!(other_session.id, id);
+        assert_eq!(other_session.events.handled, 0);
+
+        let session = tracker.track_event(false).unwrap();
+        assert_eq!(session.id, id);
+        assert_eq!(session.events.handled, 2);
+        assert_eq!(session.events.unhandled, 0);
+        // the sessions of all threads are counted
+        assert_eq!(tracker.lock().values().sum::<u64>(), 2);
+    }
+
+    #[test]
+    fn test_sessions_of_trackers() {
+        let first = SessionTracker::new();
+        let second = SessionTracker::new();
+        first.start();
+
+        assert!(first.track_event(false).is_some());
+        assert_eq!(second.track_event(false), None);
+    }
+
+    #[test]
+    fn test_send_session_counts() {
+        let tracker = SessionTracker::new();
+        tracker.start();
+        tracker.start();
+        let (delivery, transport) = delivery(202);
+        let device = DeviceInfo::new("1.0.0", "testmachine");
+
+        assert_eq!(tracker.send(&delivery, &None, &device), Ok(()));
+        // the counts were send, so there is nothing left to send
+        assert_eq!(tracker.send(&delivery, &None, &device), Ok(()));
+
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].url, "https://sessions.example.com");
+        assert!(
+            requests[0]
+                .headers
+                .contains(&("Bugsnag-Payload-Version".to_owned(), "1.0".to_owned()))
+        );
+
+        let payload: Value = serde_json::from_str(&requests[0].body).unwrap();
+        assert_eq!(payload["device"]["hostname"], "testmachine");
+        assert_eq!(payload["sessionCounts"][0]["sessionsStarted"], 2);
+    }
+
+    #[test]
+    fn test_send_keeps_counts_on_transient_failure() {
+        let tracker = SessionTracker::new();
+        tracker.start();
+        let device = DeviceInfo::new("1.0.0", "testmachine");
+
+        let (offline, _) = delivery(503);
+        assert_eq!(
+            tracker.send(&offline, &None, &device),
+            Err(Error::ServerError(503))
+        );
+
+        let (online, transport) = delivery(202);
+        assert_eq!(tracker.send(&online, &None, &device), Ok(()));
+        let payload: Value = serde_json::from_str(&transport.requests()[0].body).unwrap();
+        assert_eq!(payload["sessionCounts"][0]["sessionsStarted"], 1);
+    }
+}
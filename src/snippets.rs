@@ -0,0 +1,186 @@
+//! Module for adding the surrounding source code to the frames of a stacktrace.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+
+use super::stacktrace::Frame;
+
+/// Settings for the code snippets that are included in the frames that belong to the
+/// project. The snippets are read from the source files on the host, so they are only
+/// available where the sources are deployed, e.g. in development and staging.
+///
+/// Bugsnag shows up to seven lines of code per frame.
+///
+/// The source files are read on the thread that sends the notification, so files that are
+/// larger than the maximum file size are skipped.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodeSnippets {
+    lines_around: u32,
+    max_line_length: usize,
+    max_file_size: u64,
+}
+
+impl CodeSnippets {
+    /// Creates the default settings: three lines before and after the line of the frame,
+    /// lines are truncated after 200 characters and files larger than 1 MiB are skipped.
+    pub fn new() -> CodeSnippets {
+        CodeSnippets {
+            lines_around: 3,
+            max_line_length: 200,
+            max_file_size: 1024 * 1024,
+        }
+    }
+
+    /// Sets the number of lines before and after the line of the frame.
+    pub fn lines_around(mut self, lines_around: u32) -> Self {
+        self.lines_around = lines_around;
+        self
+    }
+
+    /// Sets the maximum number of characters of a line, longer lines are truncated.
+    pub fn max_line_length(mut self, max_line_length: usize) -> Self {
+        self.max_line_length = max_line_length;
+        self
+    }
+
+    /// Sets the maximum size in bytes of a source file, larger files get no snippets.
+    pub fn max_file_size(mut self, max_file_size: u64) -> Self {
+        self.max_file_size = max_file_size;
+        self
+    }
+
+    /// Adds the code snippets to the frames that belong to the project. Frames of files
+    /// that can not be read or are too large do not get a snippet.
+    pub fn apply(&self, frames: &mut [Frame]) {
+        // the same file is often part of multiple frames
+        let mut files: HashMap<String, Option<Vec<String>>> = HashMap::new();
+
+        for frame in frames.iter_mut().filter(|frame| frame.in_project()) {
+            let lines = files
+                .entry(frame.file().to_owned())
+                .or_insert_with(|| read_lines(frame.file(), self.max_file_size));
+
+            if let Some(ref lines) = *lines {
+                let code = self.snippet(lines, frame.line_number());
+                if !code.is_empty() {
+                    frame.set_code(code);
+                }
+            }
+        }
+    }
+
+    /// Returns the lines around the given line, by their line number.
+    fn snippet(&self, lines: &[String], line_number: u32) -> BTreeMap<u32, String> {
+        if line_number == 0 || line_number as usize > lines.len() {
+            return BTreeMap::new();
+        }
+
+        let first = line_number.saturating_sub(self.lines_around).max(1);
+        let last = line_number
+            .saturating_add(self.lines_around)
+            .min(lines.len() as u32);
+
+        (first..=last)
+            .map(|number| {
+                let line = &lines[number as usize - 1];
+                let line = match line.char_indices().nth(self.max_line_length) {
+                    Some((index, _)) => line[..index].to_owned(),
+                    None => line.clone(),
+                };
+                (number, line)
+            })
+            .collect()
+    }
+}
+
+impl Default for CodeSnippets {
+    fn default() -> CodeSnippets {
+        CodeSnippets::new()
+    }
+}
+
+fn read_lines(file: &str, max_file_size: u64) -> Option<Vec<String>> {
+    if file.is_empty() || fs::metadata(file).ok()?.len() > max_file_size {
+        return None;
+    }
+
+    let content = fs::read_to_string(file).ok()?;
+    Some(content.lines().map(|line| line.to_owned()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CodeSnippets;
+    use super::super::stacktrace::Frame;
+    use std::collections::BTreeMap;
+
+    fn lines(count: u32) -> Vec<String> {
+        (1..=count).map(|number| format!("line {}", number)).collect()
+    }
+
+    #[test]
+    fn test_snippet_around_line() {
+        let snippet = CodeSnippets::new().lines_around(2).snippet(&lines(10), 5);
+
+        let expected: BTreeMap<u32, String> = (3..=7)
+            .map(|number| (number, format!("line {}", number)))
+            .collect();
+        assert_eq!(snippet, expected);
+    }
+
+    #[test]
+    fn test_snippet_at_file_boundaries() {
+        let snippets = CodeSnippets::new();
+
+        assert_eq!(
+            snippets.snippet(&lines(10), 1).keys().collect::<Vec<_>>(),
+            vec![&1, &2, &3, &4]
+        );
+        assert_eq!(
+            snippets.snippet(&lines(10), 10).keys().collect::<Vec<_>>(),
+            vec![&7, &8, &9, &10]
+        );
+        assert!(snippets.snippet(&lines(10), 0).is_empty());
+        assert!(snippets.snippet(&lines(10), 11).is_empty());
+    }
+
+    #[test]
+    fn test_snippet_truncates_long_lines() {
+        let lines = vec!["äöü".repeat(100)];
+
+        let snippet = CodeSnippets::new().max_line_length(5).snippet(&lines, 1);
+        assert_eq!(snippet[&1], "äöüäö");
+    }
+
+    #[test]
+    fn test_apply_to_in_project_frames() {
+        let file = concat!(env!("CARGO_MANIFEST_DIR"), "/src/snippets.rs");
+        let mut frames = vec![
+            Frame::new(file, 1, "bugsnag::snippets", true),
+            Frame::new(file, 1, "bugsnag::snippets", false),
+            Frame::new("/does/not/exist.rs", 1, "bugsnag::snippets", true),
+        ];
+
+        CodeSnippets::new().lines_around(0).apply(&mut frames);
+
+        let mut expected = BTreeMap::new();
+        expected.insert(
+            1,
+            "//! Module for adding the surrounding source code to the frames of a stacktrace."
+                .to_owned(),
+        );
+        assert_eq!(frames[0].code(), Some(&expected));
+        assert_eq!(frames[1].code(), None);
+        assert_eq!(frames[2].code(), None);
+    }
+
+    #[test]
+    fn test_skip_large_files() {
+        let file = concat!(env!("CARGO_MANIFEST_DIR"), "/src/snippets.rs");
+        let mut frames = vec![Frame::new(file, 1, "bugsnag::snippets", true)];
+
+        CodeSnippets::new().max_file_size(100).apply(&mut frames);
+
+        assert_eq!(frames[0].code(), None);
+    }
+}
@@ -1,16 +1,46 @@
 //! Module for creating a stacktrace in the Bugsnag format.
 
+use std::collections::BTreeMap;
+use std::ffi::c_void;
 use std::path::Path;
 use backtrace::{self, Symbol};
 
+use super::memory_map::{self, LoadedObject, MemoryMap};
+use super::method_names;
+
+/// Defines when the addresses of a stacktrace are resolved into methods, files and lines.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Symbolication {
+    /// The addresses are resolved with the debug info of the running binary, when the
+    /// stacktrace is created.
+    #[default]
+    Immediate,
+    /// Only the addresses of the frames are recorded, together with the load address and
+    /// the build id of the object file that contains them. The addresses are resolved
+    /// later with a separate debug file, e.g. by the `bugsnag-symbolicate` tool, so that
+    /// the deployed binary can be stripped.
+    Deferred,
+}
+
 /// Struct for storing the one frame of the stacktrace.
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Frame {
     file: String,
     line_number: u32,
     method: String,
     in_project: bool,
+    #[serde(skip_serializing_if = "Option::is_none")] column_number: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")] code: Option<BTreeMap<u32, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")] frame_address: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")] symbol_address: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")] load_address: Option<String>,
+    /// The path of the object file that contains the frame.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "machoFile")]
+    object_path: Option<String>,
+    /// The build id of the object file, which identifies the matching debug file.
+    #[serde(skip_serializing_if = "Option::is_none")] build_id: Option<String>,
 }
 
 impl Frame {
@@ -20,9 +50,103 @@ impl Frame {
             line_number: linenumber,
             method: method.to_owned(),
             in_project: in_proj,
+            column_number: None,
+            code: None,
+            frame_address: None,
+            symbol_address: None,
+            load_address: None,
+            object_path: None,
+            build_id: None,
         }
     }
 
+    pub fn file(&self) -> &str {
+        &self.file
+    }
+
+    pub fn set_file(&mut self, file: String) {
+        self.file = file;
+    }
+
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    pub fn set_method(&mut self, method: String) {
+        self.method = method;
+    }
+
+    pub fn line_number(&self) -> u32 {
+        self.line_number
+    }
+
+    pub fn in_project(&self) -> bool {
+        self.in_project
+    }
+
+    pub fn code(&self) -> Option<&BTreeMap<u32, String>> {
+        self.code.as_ref()
+    }
+
+    /// Sets the source code around the line of the frame, by line number.
+    pub fn set_code(&mut self, code: BTreeMap<u32, String>) {
+        self.code = Some(code);
+    }
+
+    pub fn set_column_number(&mut self, column_number: Option<u32>) {
+        self.column_number = column_number;
+    }
+
+    /// Sets the addresses of the frame in memory and the object file that contains the
+    /// frame, which allow to symbolicate the frame later with the debug info of the object.
+    pub fn set_addresses(
+        &mut self,
+        frame_address: usize,
+        symbol_address: usize,
+        object: Option<LoadedObject>,
+    ) {
+        self.frame_address = Some(format_address(frame_address));
+        self.symbol_address = Some(format_address(symbol_address));
+        self.load_address = object
+            .as_ref()
+            .map(|object| format_address(object.load_address));
+        self.object_path = object.map(|object| object.path.to_owned());
+    }
+
+    /// Returns the address of the frame in memory and the address its object file was
+    /// loaded at, if both are known.
+    pub fn addresses(&self) -> Option<(u64, u64)> {
+        let frame_address = parse_address(self.frame_address.as_ref()?)?;
+        let load_address = parse_address(self.load_address.as_ref()?)?;
+        Some((frame_address, load_address))
+    }
+
+    pub fn build_id(&self) -> Option<&str> {
+        self.build_id.as_deref()
+    }
+
+    pub fn set_build_id(&mut self, build_id: Option<String>) {
+        self.build_id = build_id;
+    }
+
+    /// Sets the location of a frame that was symbolicated later.
+    pub fn set_location(
+        &mut self,
+        file: &str,
+        line_number: u32,
+        column_number: Option<u32>,
+        method: &str,
+    ) {
+        self.file = file.to_owned();
+        self.line_number = line_number;
+        self.column_number = column_number;
+        self.method = method.to_owned();
+    }
+
+    pub fn set_in_project(&mut self, in_project: bool) {
+        self.in_project = in_project;
+    }
+
     /// Converts from a backtrace::Symbol into a Frame
     ///
     /// # Arguments
@@ -39,67 +163,294 @@ impl Frame {
             .to_str()
             .unwrap_or("");
         let linenumber = trace.lineno().unwrap_or(0);
+        // the name is demangled here, so that it does not depend on the backtrace version
         let method = match trace.name() {
-            Some(name) => name.to_string(),
+            Some(name) => match name.as_str() {
+                Some(symbol) => method_names::demangle(symbol),
+                None => name.to_string(),
+            },
             None => "unknown".to_string(),
         };
 
-        Frame::new(
+        let mut frame = Frame::new(
             file,
             linenumber,
             method.as_str(),
             in_project(file, method.as_str()),
-        )
+        );
+        frame.set_column_number(trace.colno());
+        frame
     }
 }
 
+fn format_address(address: usize) -> String {
+    format!("0x{:x}", address)
+}
+
+fn parse_address(address: &str) -> Option<u64> {
+    let address = address.trim_start_matches("0x");
+    u64::from_str_radix(address, 16).ok()
+}
+
 /// Create a stacktrace and returns this stacktrace as vector of Frames
 ///
 /// # Arguments
 ///
 /// * `in_project` - A function that gets the following arguments (file, method). The function is
-///                  used to determine if a file and method belongs to the project.
+///   used to determine if a file and method belongs to the project.
+/// * `symbolication` - If the frames are resolved now or only their addresses are recorded.
 ///
 /// # Remarks
 ///
 /// Bugsnag will use the information about a frame belonging to a project to hide
 /// unnecessary information in the web interface.
-pub fn create_stacktrace<F>(in_project: &F) -> Vec<Frame>
+pub fn create_stacktrace<F>(in_project: &F, symbolication: Symbolication) -> Vec<Frame>
 where
     F: Fn(&str, &str) -> bool,
 {
-    let mut result: Vec<Frame> = Vec::new();
-
+    let mut addresses = Vec::new();
     backtrace::trace(|frame| {
-        backtrace::resolve(frame.ip(), |symbol| {
-            result.push(Frame::from_symbol(&symbol, in_project))
-        });
+        addresses.push((frame.ip() as usize, frame.symbol_address() as usize));
         true
     });
 
+    resolve_addresses(&addresses, in_project, symbolication)
+}
+
+/// Creates the frames of a stacktrace that was captured as the addresses of the frames
+/// and of their symbols, the top frame first.
+pub fn resolve_addresses<F>(
+    addresses: &[(usize, usize)],
+    in_project: &F,
+    symbolication: Symbolication,
+) -> Vec<Frame>
+where
+    F: Fn(&str, &str) -> bool,
+{
+    let mut result: Vec<Frame> = Vec::new();
+    let memory_map = MemoryMap::current();
+
+    for &(frame_address, symbol_address) in addresses {
+        let object = memory_map.find(frame_address);
+        let first = result.len();
+
+        if symbolication == Symbolication::Immediate {
+            backtrace::resolve(frame_address as *mut c_void, |symbol| {
+                result.push(Frame::from_symbol(symbol, in_project))
+            });
+        }
+        // the frame is kept without a symbol, so that it can be symbolicated later
+        if result.len() == first {
+            result.push(Frame::new("", 0, "unknown", in_project("", "unknown")));
+        }
+
+        // the build id is only required to find the debug file for a later symbolication
+        let build_id = match symbolication {
+            Symbolication::Immediate => None,
+            Symbolication::Deferred => object
+                .as_ref()
+                .and_then(|object| memory_map::build_id(object.path)),
+        };
+        for resolved in &mut result[first..] {
+            resolved.set_addresses(frame_address, symbol_address, object.clone());
+            resolved.set_build_id(build_id.clone());
+        }
+    }
+
+    result
+}
+
+/// Method prefixes of the frames at the top of a stacktrace, that belong to the notifier
+/// or to the panic machinery.
+const DEFAULT_LEADING_PREFIXES: &[&str] = &[
+    "backtrace::",
+    "bugsnag::",
+    "std::panicking::begin_panic",
+    "std::panicking::panic_handler",
+    "std::panicking::panic_with_hook",
+    "std::panicking::rust_panic_with_hook",
+    "std::panicking::default_hook",
+    "std::panic::panic_any",
+    "std::sys::backtrace::__rust_end_short_backtrace",
+    "std::sys_common::backtrace::__rust_end_short_backtrace",
+    "rust_begin_unwind",
+    "__rustc::rust_begin_unwind",
+    "core::panicking::",
+    "core::option::unwrap_failed",
+    "core::option::expect_failed",
+    "core::result::unwrap_failed",
+    "core::option::Option<T>::unwrap",
+    "core::option::Option<T>::expect",
+    "core::result::Result<T,E>::unwrap",
+    "core::result::Result<T,E>::expect",
+];
+
+/// Method prefixes of the frames at the bottom of a stacktrace, that belong to the
+/// runtime that starts the main function or a thread.
+const DEFAULT_TRAILING_PREFIXES: &[&str] = &[
+    "_start",
+    "__libc_start_main",
+    "__libc_start_main_impl",
+    "__libc_start_call_main",
+    "main",
+    "start_thread",
+    "clone",
+    "clone3",
+    "__clone",
+    "std::rt::",
+    "std::sys::",
+    "std::sys_common::",
+    "std::thread::",
+    "std::panic::catch_unwind",
+    "std::panicking::try",
+    "std::panicking::catch_unwind",
+    "__rust_try",
+    "core::ops::function::FnOnce::call_once",
+    "<core::panic::unwind_safe::AssertUnwindSafe<F> as core::ops::function::FnOnce<()>>",
+    "<alloc::boxed::Box<",
+    "<std::sys::",
+];
+
+/// Rules for removing the frames at the top and the bottom of a stacktrace, that do not
+/// belong to the application.
+///
+/// At the top, all frames up to the last frame that matches one of the leading prefixes
+/// are removed, e.g. the frames of the notifier and of the panic machinery. At the bottom,
+/// all frames that match one of the trailing prefixes are removed, e.g. the frames of the
+/// runtime that starts the main function.
+///
+/// A prefix matches whole path segments: `main` matches `main` and `main::{{closure}}`,
+/// but not `main_loop`. Prefixes that end with `::` or `<` match everything below them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrimRules {
+    leading: Vec<String>,
+    trailing: Vec<String>,
+}
+
+impl TrimRules {
+    /// Creates rules that do not remove any frame.
+    pub fn none() -> TrimRules {
+        TrimRules {
+            leading: Vec::new(),
+            trailing: Vec::new(),
+        }
+    }
+
+    /// Adds a method prefix of frames that are removed at the top of a stacktrace,
+    /// e.g. the functions of an application that report errors to Bugsnag.
+    pub fn leading_prefix(mut self, prefix: &str) -> Self {
+        self.leading.push(prefix.to_owned());
+        self
+    }
+
+    /// Adds a method prefix of frames that are removed at the bottom of a stacktrace.
+    pub fn trailing_prefix(mut self, prefix: &str) -> Self {
+        self.trailing.push(prefix.to_owned());
+        self
+    }
+
+    /// Removes the frames at the top and the bottom of the stacktrace. If no frame would
+    /// remain, the stacktrace is not changed.
+    pub fn trim(&self, mut frames: Vec<Frame>) -> Vec<Frame> {
+        let matches = |frame: &Frame, prefixes: &[String]| {
+            let method = strip_hashes(&frame.method);
+            prefixes.iter().any(|prefix| matches_prefix(&method, prefix))
+        };
+
+        let end = frames
+            .iter()
+            .rposition(|frame| !matches(frame, &self.trailing))
+            .map_or(0, |index| index + 1);
+        let start = frames[..end]
+            .iter()
+            .rposition(|frame| matches(frame, &self.leading))
+            .map_or(0, |index| index + 1);
+
+        if start < end {
+            frames.truncate(end);
+            frames.drain(..start);
+        }
+        frames
+    }
+}
+
+/// Returns if the method starts with the prefix and the prefix ends at the boundary of a
+/// path segment.
+fn matches_prefix(method: &str, prefix: &str) -> bool {
+    if !method.starts_with(prefix) {
+        return false;
+    }
+
+    let rest = &method[prefix.len()..];
+    prefix.ends_with("::")
+        || prefix.ends_with('<')
+        || rest.is_empty()
+        || rest.starts_with("::")
+        || rest.starts_with('<')
+        || rest.starts_with('{')
+}
+
+impl Default for TrimRules {
+    /// Removes the frames of the notifier, of the panic machinery and of the runtime.
+    fn default() -> TrimRules {
+        TrimRules {
+            leading: DEFAULT_LEADING_PREFIXES.iter().map(|p| (*p).to_owned()).collect(),
+            trailing: DEFAULT_TRAILING_PREFIXES.iter().map(|p| (*p).to_owned()).collect(),
+        }
+    }
+}
+
+/// Removes the hashes from a symbol name, i.e. the crate disambiguators like
+/// `std[e28293b1aa0f68bd]` and the hash suffix like `::h4a7e324bba05a745`.
+pub fn strip_hashes(method: &str) -> String {
+    let mut result = String::with_capacity(method.len());
+    let mut rest = method;
+
+    while let Some(open) = rest.find('[') {
+        result.push_str(&rest[..open]);
+
+        let after = &rest[open + 1..];
+        let hash_len = after
+            .find(|c: char| !c.is_ascii_hexdigit())
+            .unwrap_or(after.len());
+        if hash_len >= 8 && after[hash_len..].starts_with(']') {
+            rest = &after[hash_len + 1..];
+        } else {
+            // not a hash, e.g. a slice type like `[u8]`
+            result.push('[');
+            rest = after;
+        }
+    }
+    result.push_str(rest);
+
+    if let Some(index) = result.rfind("::h") {
+        let hash = &result[index + 3..];
+        if hash.len() == 16 && hash.chars().all(|c| c.is_ascii_hexdigit()) {
+            result.truncate(index);
+        }
+    }
     result
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{create_stacktrace, Frame};
+    use super::{create_stacktrace, strip_hashes, Frame, LoadedObject, Symbolication, TrimRules};
     use serde_test::{assert_ser_tokens, Token};
 
     #[test]
     #[ignore]
     fn test_create_stacktrace() {
         let file = file!();
-        let frames = create_stacktrace(&|f, _| f.ends_with(&file));
+        let frames = create_stacktrace(&|f, _| f.ends_with(&file), Symbolication::Immediate);
         let mut found_frame = false;
 
         for frame in frames {
-            if frame.method == "bugsnag::stacktrace::tests::test_create_stacktrace" {
-                if frame.file.ends_with(file) {
-                    if frame.in_project {
-                        found_frame = true;
-                        break;
-                    }
-                }
+            if frame.method == "bugsnag::stacktrace::tests::test_create_stacktrace"
+                && frame.file.ends_with(file)
+                && frame.in_project
+            {
+                found_frame = true;
+                break;
             }
         }
 
@@ -133,22 +484,246 @@ mod tests {
     #[test]
     #[ignore]
     fn test_create_stacktrace_with_ignore() {
-        let frames =
-            create_stacktrace(&|_, method| !method.contains("test_create_stacktrace_with_ignore"));
+        let frames = create_stacktrace(
+            &|_, method| !method.contains("test_create_stacktrace_with_ignore"),
+            Symbolication::Immediate,
+        );
         let mut found_frame = false;
         let file = file!();
 
         for frame in frames {
-            if frame.method == "bugsnag::stacktrace::tests::test_create_stacktrace_with_ignore" {
-                if frame.file.ends_with(file) {
-                    if frame.in_project == false {
-                        found_frame = true;
-                        break;
-                    }
-                }
+            if frame.method == "bugsnag::stacktrace::tests::test_create_stacktrace_with_ignore"
+                && frame.file.ends_with(file)
+                && !frame.in_project
+            {
+                found_frame = true;
+                break;
             }
         }
 
         assert!(found_frame);
     }
+
+    #[test]
+    fn test_frame_with_addresses_to_json() {
+        let mut frame = Frame::new("test.rs", 500, "test_json", false);
+        frame.set_column_number(Some(12));
+        frame.set_addresses(
+            0x55d0c1a30010,
+            0x55d0c1a30000,
+            Some(LoadedObject {
+                load_address: 0x55d0c1a00000,
+                path: "/usr/bin/app",
+            }),
+        );
+
+        assert_ser_tokens(
+            &frame,
+            &[
+                Token::Struct {
+                    name: "Frame",
+                    len: 9,
+                },
+                Token::Str("file"),
+                Token::Str("test.rs"),
+                Token::Str("lineNumber"),
+                Token::U32(500),
+                Token::Str("method"),
+                Token::Str("test_json"),
+                Token::Str("inProject"),
+                Token::Bool(false),
+                Token::Str("columnNumber"),
+                Token::Some,
+                Token::U32(12),
+                Token::Str("frameAddress"),
+                Token::Some,
+                Token::Str("0x55d0c1a30010"),
+                Token::Str("symbolAddress"),
+                Token::Some,
+                Token::Str("0x55d0c1a30000"),
+                Token::Str("loadAddress"),
+                Token::Some,
+                Token::Str("0x55d0c1a00000"),
+                Token::Str("machoFile"),
+                Token::Some,
+                Token::Str("/usr/bin/app"),
+                Token::StructEnd,
+            ],
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_create_stacktrace_with_addresses() {
+        let frames = create_stacktrace(&|_, _| false, Symbolication::Immediate);
+        let exe = ::std::env::current_exe().unwrap();
+
+        assert!(frames.iter().all(|frame| frame.frame_address.is_some()));
+        assert!(
+            frames
+                .iter()
+                .any(|frame| frame.object_path.as_ref().map(|path| path.as_ref()) == exe.to_str())
+        );
+        assert!(frames.iter().all(|frame| frame.build_id.is_none()));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_create_deferred_stacktrace() {
+        let frames = create_stacktrace(&|_, _| false, Symbolication::Deferred);
+        let exe = ::std::env::current_exe().unwrap();
+
+        assert!(frames.iter().all(|frame| frame.method == "unknown"));
+        assert!(frames.iter().all(|frame| frame.line_number == 0));
+
+        let frame = frames
+            .iter()
+            .find(|frame| frame.object_path.as_ref().map(|path| path.as_ref()) == exe.to_str())
+            .unwrap();
+        let (frame_address, load_address) = frame.addresses().unwrap();
+        assert!(load_address <= frame_address);
+        assert_eq!(
+            frame.build_id,
+            super::memory_map::build_id(exe.to_str().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_frame_from_json() {
+        let json = r#"{
+            "file": "",
+            "lineNumber": 0,
+            "method": "unknown",
+            "inProject": false,
+            "frameAddress": "0x55d0c1a30010",
+            "loadAddress": "0x55d0c1a00000",
+            "buildId": "0abc"
+        }"#;
+
+        let frame: Frame = ::serde_json::from_str(json).unwrap();
+        assert_eq!(frame.addresses(), Some((0x55d0c1a30010, 0x55d0c1a00000)));
+        assert_eq!(frame.build_id(), Some("0abc"));
+        assert_eq!(frame.code(), None);
+    }
+
+    fn frames(methods: &[&str]) -> Vec<Frame> {
+        methods
+            .iter()
+            .map(|method| Frame::new("test.rs", 1, method, false))
+            .collect()
+    }
+
+    fn methods(frames: &[Frame]) -> Vec<&str> {
+        frames.iter().map(|frame| frame.method.as_str()).collect()
+    }
+
+    #[test]
+    fn test_strip_hashes() {
+        assert_eq!(
+            strip_hashes("std[e28293b1aa0f68bd]::panicking::panic_with_hook"),
+            "std::panicking::panic_with_hook"
+        );
+        assert_eq!(
+            strip_hashes("std::panic::catch_unwind::haa2493065a96a9d0"),
+            "std::panic::catch_unwind"
+        );
+        assert_eq!(
+            strip_hashes("<[u8] as core::fmt::Debug>::fmt"),
+            "<[u8] as core::fmt::Debug>::fmt"
+        );
+        assert_eq!(strip_hashes("my_app::hash"), "my_app::hash");
+    }
+
+    #[test]
+    fn test_trim_notifier_and_panic_frames() {
+        let trimmed = TrimRules::default().trim(frames(&[
+            "backtrace::backtrace::trace::hbd7640df48dfa43e",
+            "bugsnag::stacktrace::create_stacktrace::h2f4935f5ba5de06c",
+            "bugsnag::panic::handle::h2f4935f5ba5de06c",
+            "my_app::main::{{closure}}::h2f4935f5ba5de06c",
+            "std[e28293b1aa0f68bd]::panicking::panic_with_hook",
+            "__rustc[b7974e8690430dd9]::rust_begin_unwind",
+            "core[c1f1a4ba060b9bfa]::panicking::panic_fmt",
+            "core[c1f1a4ba060b9bfa]::option::unwrap_failed",
+            "core::option::Option<T>::unwrap::h9dafa51c5fde8052",
+            "my_app::run::h8ce65fa21d299fbd",
+            "my_app::main::h8ce65fa21d299fbd",
+            "core::ops::function::FnOnce::call_once::h8a1043984610d15d",
+            "std::sys::backtrace::__rust_begin_short_backtrace::h8a1043984610d15d",
+            "std::rt::lang_start::{{closure}}::h8a1043984610d15d",
+            "std::rt::lang_start_internal::h8a1043984610d15d",
+            "main",
+            "__libc_start_call_main",
+            "__libc_start_main_impl",
+            "_start",
+        ]));
+
+        assert_eq!(
+            methods(&trimmed),
+            vec!["my_app::run::h8ce65fa21d299fbd", "my_app::main::h8ce65fa21d299fbd"]
+        );
+    }
+
+    #[test]
+    fn test_trim_thread_frames() {
+        let trimmed = TrimRules::default().trim(frames(&[
+            "bugsnag::bugsnag_impl::NotifyBuilder::send::h2f4935f5ba5de06c",
+            "my_app::worker::h8ce65fa21d299fbd",
+            "std::sys::backtrace::__rust_begin_short_backtrace::h8a1043984610d15d",
+            "std::thread::lifecycle::spawn_unchecked::{{closure}}::h3f791bd54d12cfa5",
+            "__rust_try",
+            "std::panic::catch_unwind::haa2493065a96a9d0",
+            "<std[e28293b1aa0f68bd]::sys::thread::unix::Thread>::new::thread_start",
+            "start_thread",
+            "clone3",
+        ]));
+
+        assert_eq!(methods(&trimmed), vec!["my_app::worker::h8ce65fa21d299fbd"]);
+    }
+
+    #[test]
+    fn test_trim_with_extra_prefixes() {
+        let rules = TrimRules::default()
+            .leading_prefix("my_app::report")
+            .trailing_prefix("my_app::main");
+
+        let trimmed = rules.trim(frames(&[
+            "bugsnag::bugsnag_impl::NotifyBuilder::send",
+            "my_app::report",
+            "my_app::handle_request",
+            "my_app::main",
+            "main",
+        ]));
+
+        assert_eq!(methods(&trimmed), vec!["my_app::handle_request"]);
+    }
+
+    #[test]
+    fn test_trim_keeps_user_functions_with_runtime_prefixes() {
+        let trimmed = TrimRules::default().trim(frames(&[
+            "my_app::clone_repo",
+            "main_loop",
+            "clone_repo",
+            "mainly::run",
+            "main::{{closure}}",
+            "main",
+            "clone",
+        ]));
+
+        assert_eq!(
+            methods(&trimmed),
+            vec!["my_app::clone_repo", "main_loop", "clone_repo", "mainly::run"]
+        );
+    }
+
+    #[test]
+    fn test_trim_keeps_everything_instead_of_nothing() {
+        let original = frames(&["bugsnag::send", "main"]);
+
+        assert_eq!(
+            methods(&TrimRules::default().trim(frames(&["bugsnag::send", "main"]))),
+            methods(&original)
+        );
+        assert_eq!(methods(&TrimRules::none().trim(frames(&["main"]))), vec!["main"]);
+    }
 }
@@ -0,0 +1,428 @@
+//! Module for resolving the addresses of stacktraces, that were created with a deferred
+//! symbolication, with a separate debug file.
+
+use addr2line::{self, Loader};
+use serde_json::{self, Value};
+use std::borrow::Cow;
+use std::error::Error as StdError;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::frame_filter::ProjectFrameFilter;
+use super::memory_map::ObjectInfo;
+use super::method_names::MethodNames;
+use super::outbox::{EXTENSION, UNRESOLVED_EXTENSION};
+use super::path_normalizer::PathNormalizer;
+use super::stacktrace::{Frame, TrimRules};
+
+#[derive(Debug)]
+pub enum SymbolicationError {
+    /// The debug file could not be read or is not a supported object file.
+    InvalidDebugFile(String),
+    /// A stored notification could not be read or written.
+    Io(io::Error),
+    /// A stored notification is not a valid notification json.
+    InvalidJson,
+}
+
+impl fmt::Display for SymbolicationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SymbolicationError::InvalidDebugFile(ref path) => {
+                write!(f, "the debug file {} can not be read", path)
+            }
+            SymbolicationError::Io(ref err) => {
+                write!(f, "reading or writing the notification failed: {}", err)
+            }
+            SymbolicationError::InvalidJson => write!(f, "the notification is not valid json"),
+        }
+    }
+}
+
+impl StdError for SymbolicationError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match *self {
+            SymbolicationError::Io(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SymbolicationError {
+    fn from(err: io::Error) -> SymbolicationError {
+        SymbolicationError::Io(err)
+    }
+}
+
+/// Resolves the stacktraces of notifications, that were created with
+/// `Symbolication::Deferred`, with the debug file of the binary, e.g. a file created with
+/// `objcopy --only-keep-debug`. Only the frames with the build id of the debug file are
+/// resolved, the frames of other object files, like the system libraries, are not changed.
+///
+/// Inlined functions are resolved into separate frames. The resolved stacktraces are
+/// trimmed, filtered and normalized like the stacktraces that are resolved immediately.
+///
+/// # Example
+///
+/// ```no_run
+/// let symbolicator = bugsnag::Symbolicator::new("target/release/my_app.debug")
+///     .unwrap()
+///     .project_root("/home/ci/my_app");
+///
+/// // the outbox of the application, before it is delivered with `send_outbox`
+/// symbolicator.symbolicate_dir("/var/lib/my_app/bugsnag").unwrap();
+/// ```
+pub struct Symbolicator {
+    loader: Loader,
+    info: ObjectInfo,
+    frame_filter: ProjectFrameFilter,
+    path_normalizer: PathNormalizer,
+    trim_rules: TrimRules,
+    method_names: MethodNames,
+}
+
+impl Symbolicator {
+    /// Creates a symbolicator for the given debug file. By default, no frame belongs to
+    /// the project and the default trim rules are used.
+    pub fn new<P: AsRef<Path>>(debug_file: P) -> Result<Symbolicator, SymbolicationError> {
+        let path = debug_file.as_ref();
+        let invalid = || SymbolicationError::InvalidDebugFile(path.display().to_string());
+
+        let info = ObjectInfo::read(path).ok_or_else(invalid)?;
+        let loader = Loader::new(path).map_err(|_| invalid())?;
+
+        Ok(Symbolicator {
+            loader,
+            info,
+            frame_filter: ProjectFrameFilter::new(),
+            path_normalizer: PathNormalizer::new(),
+            trim_rules: TrimRules::default(),
+            method_names: MethodNames::new(),
+        })
+    }
+
+    /// Sets the directory the binary was build in. Frames of files in this directory
+    /// belong to the project and the directory is removed from their paths.
+    pub fn project_root(mut self, dir: &str) -> Self {
+        self.frame_filter = self.frame_filter.source_root(dir);
+        self.path_normalizer = self.path_normalizer.prefix(dir);
+        self
+    }
+
+    /// Sets the filter that decides which resolved frames belong to the project.
+    pub fn frame_filter(mut self, frame_filter: ProjectFrameFilter) -> Self {
+        self.frame_filter = frame_filter;
+        self
+    }
+
+    /// Sets the normalizer for the paths of the resolved frames.
+    pub fn path_normalizer(mut self, path_normalizer: PathNormalizer) -> Self {
+        self.path_normalizer = path_normalizer;
+        self
+    }
+
+    /// Sets the rules for trimming the resolved stacktraces.
+    pub fn trim_rules(mut self, trim_rules: TrimRules) -> Self {
+        self.trim_rules = trim_rules;
+        self
+    }
+
+    /// Sets how the method names of the resolved frames are cleaned up.
+    pub fn method_names(mut self, method_names: MethodNames) -> Self {
+        self.method_names = method_names;
+        self
+    }
+
+    /// Resolves the stacktraces of the exceptions and threads of a notification json and
+    /// returns the changed json.
+    pub fn symbolicate_json(&self, json: &str) -> Result<String, SymbolicationError> {
+        let mut notification: Value =
+            serde_json::from_str(json).map_err(|_| SymbolicationError::InvalidJson)?;
+        let events = notification
+            .get_mut("events")
+            .and_then(Value::as_array_mut)
+            .ok_or(SymbolicationError::InvalidJson)?;
+
+        for event in events {
+            for key in &["exceptions", "threads"] {
+                let entries = match event.get_mut(*key).and_then(Value::as_array_mut) {
+                    Some(entries) => entries,
+                    None => continue,
+                };
+
+                for stacktrace in entries.iter_mut().filter_map(|e| e.get_mut("stacktrace")) {
+                    let frames: Vec<Frame> = serde_json::from_value(stacktrace.take())
+                        .map_err(|_| SymbolicationError::InvalidJson)?;
+                    *stacktrace = serde_json::to_value(self.symbolicate(frames))
+                        .map_err(|_| SymbolicationError::InvalidJson)?;
+                }
+            }
+        }
+
+        serde_json::to_string(&notification).map_err(|_| SymbolicationError::InvalidJson)
+    }
+
+    /// Resolves the stacktraces of a notification that is stored in a file. The file is
+    /// replaced with the changed json. An unresolved notification of an outbox is replaced
+    /// with a pending notification, so that it is delivered by `send_outbox`.
+    pub fn symbolicate_file<P: AsRef<Path>>(&self, path: P) -> Result<(), SymbolicationError> {
+        let path = path.as_ref();
+        let json = self.symbolicate_json(&fs::read_to_string(path)?)?;
+
+        // the file is replaced at once, so that a partially written notification is
+        // never delivered
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, json)?;
+        if path.extension().and_then(|ext| ext.to_str()) == Some(UNRESOLVED_EXTENSION) {
+            fs::rename(&tmp_path, path.with_extension(EXTENSION))?;
+            fs::remove_file(path)?;
+        } else {
+            fs::rename(&tmp_path, path)?;
+        }
+        Ok(())
+    }
+
+    /// Resolves the stacktraces of all unresolved notifications that are stored in a
+    /// directory, e.g. in an outbox. Returns the number of notifications.
+    pub fn symbolicate_dir<P: AsRef<Path>>(&self, dir: P) -> Result<usize, SymbolicationError> {
+        let mut count = 0;
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) == Some(UNRESOLVED_EXTENSION) {
+                self.symbolicate_file(&path)?;
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    /// Resolves the frames that belong to the debug file and trims the stacktrace.
+    fn symbolicate(&self, frames: Vec<Frame>) -> Vec<Frame> {
+        let mut result = Vec::with_capacity(frames.len());
+
+        for frame in frames {
+            let resolved = match self.object_address(&frame) {
+                Some(address) => self.resolve(&frame, address),
+                None => Vec::new(),
+            };
+
+            if resolved.is_empty() {
+                result.push(frame);
+            } else {
+                result.extend(resolved);
+            }
+        }
+
+        self.trim_rules.trim(result)
+    }
+
+    /// Returns the address of a frame in the debug file, if the frame was not resolved
+    /// yet and belongs to the debug file.
+    fn object_address(&self, frame: &Frame) -> Option<u64> {
+        if frame.method() != "unknown" || frame.build_id() != self.info.build_id.as_deref() {
+            return None;
+        }
+
+        let (frame_address, load_address) = frame.addresses()?;
+        object_address(frame_address, load_address, self.info.base_address)
+    }
+
+    /// Returns the frames of the functions at the address, the innermost inlined
+    /// function first.
+    fn resolve(&self, frame: &Frame, address: u64) -> Vec<Frame> {
+        let mut resolved = Vec::new();
+
+        if let Ok(mut locations) = self.loader.find_frames(address) {
+            while let Ok(Some(location)) = locations.next() {
+                let method = match location.function {
+                    Some(ref function) => function.demangle().ok().map(Cow::into_owned),
+                    None => None,
+                };
+                let method = method.or_else(|| self.symbol(address));
+                let (file, line_number, column_number) = match location.location {
+                    Some(location) => (
+                        location.file.unwrap_or(""),
+                        location.line.unwrap_or(0),
+                        location.column,
+                    ),
+                    None => ("", 0, None),
+                };
+
+                resolved.push(self.resolved_frame(
+                    frame,
+                    file,
+                    line_number,
+                    column_number,
+                    method.as_ref().map_or("unknown", |method| method.as_str()),
+                ));
+            }
+        }
+
+        // without debug info for the address, the symbol table might still know the method
+        if resolved.is_empty() {
+            if let Some(method) = self.symbol(address) {
+                resolved.push(self.resolved_frame(frame, "", 0, None, &method));
+            }
+        }
+        resolved
+    }
+
+    /// Returns the demangled name of the symbol that contains the address.
+    fn symbol(&self, address: u64) -> Option<String> {
+        let name = self.loader.find_symbol(address)?;
+        Some(addr2line::demangle_auto(Cow::from(name), None).into_owned())
+    }
+
+    fn resolved_frame(
+        &self,
+        frame: &Frame,
+        file: &str,
+        line_number: u32,
+        column_number: Option<u32>,
+        method: &str,
+    ) -> Frame {
+        let method = self.method_names.clean(method);
+        let mut resolved = frame.clone();
+        resolved.set_in_project(self.frame_filter.is_in_project(file, &method));
+        resolved.set_location(
+            &self.path_normalizer.normalize(file),
+            line_number,
+            column_number,
+            &method,
+        );
+        resolved
+    }
+}
+
+/// Converts the address of a frame in memory into the address in the object file, that
+/// was loaded at the given address.
+///
+/// The address of a frame is the return address of the call, which might already belong
+/// to the next line or function, so the address of the previous byte is returned.
+fn object_address(frame_address: u64, load_address: u64, base_address: u64) -> Option<u64> {
+    let offset = frame_address.checked_sub(load_address)?;
+    base_address.checked_add(offset)?.checked_sub(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{object_address, Symbolicator, SymbolicationError};
+    use super::super::outbox::Outbox;
+    use super::super::stacktrace::{self, Symbolication, TrimRules};
+    use serde_json::{self, json, Value};
+
+    #[test]
+    fn test_object_address() {
+        // a position independent executable starts at address 0
+        assert_eq!(object_address(0x55d0c1a30010, 0x55d0c1a00000, 0), Some(0x3000f));
+        assert_eq!(object_address(0x401010, 0x400000, 0x400000), Some(0x40100f));
+        assert_eq!(object_address(0x1000, 0x2000, 0), None);
+        assert_eq!(object_address(0x2000, 0x2000, 0), None);
+    }
+
+    #[test]
+    fn test_invalid_debug_file() {
+        match Symbolicator::new("/does/not/exist.debug") {
+            Err(SymbolicationError::InvalidDebugFile(path)) => {
+                assert_eq!(path, "/does/not/exist.debug")
+            }
+            _ => panic!("the debug file does not exist"),
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn current_exe() -> Symbolicator {
+        Symbolicator::new(::std::env::current_exe().unwrap())
+            .unwrap()
+            .project_root(env!("CARGO_MANIFEST_DIR"))
+            .trim_rules(TrimRules::none())
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_symbolicate_deferred_stacktrace() {
+        let frames = stacktrace::create_stacktrace(&|_, _| false, Symbolication::Deferred);
+        let json = serde_json::to_string(&json!({
+            "apiKey": "api-key",
+            "events": [{
+                "exceptions": [{ "errorClass": "Error", "stacktrace": frames }],
+                "threads": [{ "id": "1", "stacktrace": [] }],
+            }],
+        })).unwrap();
+
+        let symbolicated: Value =
+            serde_json::from_str(&current_exe().symbolicate_json(&json).unwrap()).unwrap();
+
+        let frames = symbolicated["events"][0]["exceptions"][0]["stacktrace"]
+            .as_array()
+            .unwrap();
+        let frame = frames
+            .iter()
+            .find(|frame| {
+                frame["method"]
+                    .as_str()
+                    .unwrap()
+                    .contains("test_symbolicate_deferred_stacktrace")
+            })
+            .unwrap();
+        assert_eq!(frame["file"], "src/symbolicate.rs");
+        assert_eq!(frame["inProject"], true);
+        assert!(frame["lineNumber"].as_u64().unwrap() > 0);
+        assert!(frame["frameAddress"].is_string());
+        assert_eq!(symbolicated["events"][0]["threads"][0]["stacktrace"], json!([]));
+        assert_eq!(symbolicated["apiKey"], "api-key");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_frames_of_other_objects_are_not_changed() {
+        let frame = json!({
+            "file": "",
+            "lineNumber": 0,
+            "method": "unknown",
+            "inProject": false,
+            "frameAddress": "0x7f1e2a030000",
+            "loadAddress": "0x7f1e2a000000",
+            "machoFile": "/usr/lib/libc.so.6",
+            "buildId": "0123456789abcdef",
+        });
+        let json = json!({ "events": [{ "exceptions": [{ "stacktrace": [frame] }] }] });
+
+        let symbolicated: Value = serde_json::from_str(
+            &current_exe()
+                .symbolicate_json(&json.to_string())
+                .unwrap(),
+        ).unwrap();
+        assert_eq!(symbolicated, json);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_symbolicate_dir_makes_notifications_pending() {
+        let dir = ::std::env::temp_dir()
+            .join(format!("bugsnag-symbolicate-dir-{}", ::std::process::id()));
+        let _ = ::std::fs::remove_dir_all(&dir);
+        let outbox = Outbox::new(&dir);
+        let frames = stacktrace::create_stacktrace(&|_, _| false, Symbolication::Deferred);
+        let json = json!({ "events": [{ "exceptions": [{ "stacktrace": frames }] }] });
+        let unresolved = outbox.store_unresolved(&json.to_string()).unwrap();
+
+        assert_eq!(current_exe().symbolicate_dir(&dir).unwrap(), 1);
+        assert!(!unresolved.exists());
+        assert_eq!(outbox.pending().unwrap(), vec![unresolved.with_extension("json")]);
+        // the pending notifications are not symbolicated again
+        assert_eq!(current_exe().symbolicate_dir(&dir).unwrap(), 0);
+        let _ = ::std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_invalid_json() {
+        match current_exe().symbolicate_json(r#"{"apiKey": "api-key"}"#) {
+            Err(SymbolicationError::InvalidJson) => {}
+            _ => panic!("the json has no events"),
+        }
+    }
+}
@@ -0,0 +1,449 @@
+//! Module for capturing the threads of the process at the time of an error.
+//!
+//! On Linux, the stacktraces of the other threads are captured by sending a signal to
+//! every thread, whose handler records the addresses of the frames of the thread. The
+//! signal is the real-time signal `SIGRTMIN + 4`. If the application installed its own
+//! handler for this signal, the other threads are reported without stacktraces.
+//!
+//! Unwinding a thread from a signal handler is best effort: a thread that blocks the
+//! signal or does not handle it in time is reported without a stacktrace.
+
+use super::stacktrace::Frame;
+
+#[cfg(target_os = "linux")]
+use std::ffi::c_void;
+#[cfg(target_os = "linux")]
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicUsize, Ordering};
+#[cfg(target_os = "linux")]
+use std::sync::{Mutex, Once};
+#[cfg(target_os = "linux")]
+use std::time::{Duration, Instant};
+#[cfg(target_os = "linux")]
+use std::{fs, mem, ptr};
+use std::thread;
+
+#[cfg(target_os = "linux")]
+use backtrace;
+#[cfg(target_os = "linux")]
+use libc;
+
+/// The maximum number of frames of the stacktrace of another thread.
+#[cfg(target_os = "linux")]
+const MAX_FRAMES: usize = 256;
+
+/// The time a thread has to record its stacktrace after it was signaled.
+#[cfg(target_os = "linux")]
+const THREAD_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// The time after which the stacktraces of the remaining threads are not captured anymore.
+#[cfg(target_os = "linux")]
+const CAPTURE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The states of a capture, besides the id of the thread that is asked to record its
+/// stacktrace.
+#[cfg(target_os = "linux")]
+const IDLE: i32 = 0;
+#[cfg(target_os = "linux")]
+const RECORDING: i32 = -1;
+#[cfg(target_os = "linux")]
+const RECORDED: i32 = -2;
+
+/// The state of the current capture. The signal handler only records the stacktrace if it
+/// runs on the requested thread, so a signal that is handled too late is ignored.
+#[cfg(target_os = "linux")]
+static REQUEST: AtomicI32 = AtomicI32::new(IDLE);
+
+/// The stacktrace recorded by the signal handler, as frame and symbol addresses. The
+/// handler must not allocate, so the frames are stored in a static buffer.
+#[cfg(target_os = "linux")]
+static FRAME_COUNT: AtomicUsize = AtomicUsize::new(0);
+#[cfg(target_os = "linux")]
+static FRAME_ADDRESSES: [AtomicUsize; MAX_FRAMES] = [const { AtomicUsize::new(0) }; MAX_FRAMES];
+#[cfg(target_os = "linux")]
+static SYMBOL_ADDRESSES: [AtomicUsize; MAX_FRAMES] = [const { AtomicUsize::new(0) }; MAX_FRAMES];
+
+/// Only one capture can use the buffer at a time.
+#[cfg(target_os = "linux")]
+static CAPTURE_LOCK: Mutex<()> = Mutex::new(());
+
+#[cfg(target_os = "linux")]
+static INSTALL_HANDLER: Once = Once::new();
+#[cfg(target_os = "linux")]
+static HANDLER_INSTALLED: AtomicBool = AtomicBool::new(false);
+
+/// A thread of the process. The stacktraces of the other threads are only captured on
+/// Linux, on all other platforms they are reported with their name and id.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Thread {
+    id: String,
+    name: String,
+    error_reporting_thread: bool,
+    stacktrace: Vec<Frame>,
+}
+
+impl Thread {
+    pub fn new(id: &str, name: &str, error_reporting_thread: bool, stacktrace: &[Frame]) -> Thread {
+        Thread {
+            id: id.to_owned(),
+            name: name.to_owned(),
+            error_reporting_thread,
+            stacktrace: stacktrace.to_vec(),
+        }
+    }
+}
+
+/// Returns all threads of the process, sorted by their id. The given stacktrace belongs
+/// to the calling thread, which is marked as the error reporting thread. The stacktraces
+/// of the other threads are captured as the addresses of their frames and of their
+/// symbols, which `resolve` converts into frames.
+///
+/// The threads can only be enumerated on Linux, on all other platforms only the calling
+/// thread is returned.
+#[cfg_attr(not(target_os = "linux"), allow(unused_variables))]
+pub fn capture<F>(stacktrace: &[Frame], resolve: F) -> Vec<Thread>
+where
+    F: Fn(&[(usize, usize)]) -> Vec<Frame>,
+{
+    let current_name = thread::current().name().map(|name| name.to_owned());
+
+    #[cfg(target_os = "linux")]
+    {
+        if let Some(threads) = capture_linux(stacktrace, current_name.as_ref(), &resolve) {
+            return threads;
+        }
+    }
+
+    vec![
+        Thread::new(
+            &format!("{:?}", thread::current().id()),
+            current_name.as_ref().map_or("unknown", |name| name.as_str()),
+            true,
+            stacktrace,
+        ),
+    ]
+}
+
+/// Enumerates the threads of the process with `/proc/self/task` and captures the
+/// stacktraces of the other threads.
+#[cfg(target_os = "linux")]
+fn capture_linux<F>(
+    stacktrace: &[Frame],
+    current_name: Option<&String>,
+    resolve: &F,
+) -> Option<Vec<Thread>>
+where
+    F: Fn(&[(usize, usize)]) -> Vec<Frame>,
+{
+    // resolves to `<pid>/task/<tid>` for the calling thread
+    let thread_self = fs::read_link("/proc/thread-self").ok()?;
+    let current_id = thread_self.file_name()?.to_str()?.to_owned();
+
+    let mut ids: Vec<(u64, String)> = fs::read_dir("/proc/self/task")
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter_map(|id| id.parse().ok().map(|number| (number, id)))
+        .collect();
+    ids.sort();
+
+    let _capture = CAPTURE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let handler_installed = install_handler();
+    let deadline = Instant::now() + CAPTURE_TIMEOUT;
+
+    let threads = ids.into_iter()
+        .map(|(tid, id)| {
+            if id == current_id {
+                // the name in `comm` is truncated to 15 characters
+                let name = current_name
+                    .cloned()
+                    .or_else(|| thread_name(&id))
+                    .unwrap_or_else(|| "unknown".to_owned());
+                Thread::new(&id, &name, true, stacktrace)
+            } else {
+                let name = thread_name(&id).unwrap_or_else(|| "unknown".to_owned());
+                let addresses = if handler_installed {
+                    record_stacktrace(tid as libc::pid_t, deadline)
+                } else {
+                    None
+                };
+                let frames = addresses.map_or_else(Vec::new, |addresses| resolve(&addresses));
+                Thread::new(&id, &name, false, &frames)
+            }
+        })
+        .collect();
+
+    Some(threads)
+}
+
+#[cfg(target_os = "linux")]
+fn thread_name(id: &str) -> Option<String> {
+    // the thread might have exited in the meantime
+    let name = fs::read_to_string(format!("/proc/self/task/{}/comm", id)).ok()?;
+    Some(name.trim_end().to_owned())
+}
+
+/// The signal that asks a thread to record its stacktrace.
+#[cfg(target_os = "linux")]
+fn capture_signal() -> libc::c_int {
+    libc::SIGRTMIN() + 4
+}
+
+/// Installs the signal handler, unless the application handles the signal itself.
+/// Returns if the handler is installed.
+#[cfg(target_os = "linux")]
+fn install_handler() -> bool {
+    INSTALL_HANDLER.call_once(|| unsafe {
+        let mut previous: libc::sigaction = mem::zeroed();
+        if libc::sigaction(capture_signal(), ptr::null(), &mut previous) != 0
+            || previous.sa_sigaction != libc::SIG_DFL
+        {
+            return;
+        }
+
+        let mut action: libc::sigaction = mem::zeroed();
+        action.sa_sigaction = handle_signal as *const () as libc::sighandler_t;
+        action.sa_flags = libc::SA_SIGINFO | libc::SA_RESTART;
+        libc::sigemptyset(&mut action.sa_mask);
+        if libc::sigaction(capture_signal(), &action, ptr::null_mut()) == 0 {
+            HANDLER_INSTALLED.store(true, Ordering::SeqCst);
+        }
+    });
+
+    if !HANDLER_INSTALLED.load(Ordering::SeqCst) {
+        return false;
+    }
+
+    // the application might have replaced the handler in the meantime
+    unsafe {
+        let mut current: libc::sigaction = mem::zeroed();
+        libc::sigaction(capture_signal(), ptr::null(), &mut current) == 0
+            && current.sa_sigaction == handle_signal as *const () as libc::sighandler_t
+    }
+}
+
+/// Records the stacktrace of the calling thread into the static buffer, if the thread was
+/// asked to. Only async-signal-safe functions are called and nothing is allocated.
+#[cfg(target_os = "linux")]
+extern "C" fn handle_signal(_: libc::c_int, _: *mut libc::siginfo_t, _: *mut c_void) {
+    unsafe {
+        let errno = *libc::__errno_location();
+        let tid = libc::syscall(libc::SYS_gettid) as libc::pid_t;
+
+        if REQUEST
+            .compare_exchange(tid, RECORDING, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
+        {
+            let mut count = 0;
+            // the synchronized trace takes a lock, that the thread might hold already
+            backtrace::trace_unsynchronized(|frame| {
+                FRAME_ADDRESSES[count].store(frame.ip() as usize, Ordering::Relaxed);
+                SYMBOL_ADDRESSES[count].store(frame.symbol_address() as usize, Ordering::Relaxed);
+                count += 1;
+                count < MAX_FRAMES
+            });
+            FRAME_COUNT.store(count, Ordering::Relaxed);
+            REQUEST.store(RECORDED, Ordering::Release);
+        }
+
+        *libc::__errno_location() = errno;
+    }
+}
+
+/// Asks the thread with the given id to record its stacktrace and returns the addresses
+/// of its frames and symbols, without the frames of the signal handler. Returns `None`
+/// if the thread did not record its stacktrace in time.
+#[cfg(target_os = "linux")]
+fn record_stacktrace(tid: libc::pid_t, deadline: Instant) -> Option<Vec<(usize, usize)>> {
+    // the handler of a thread that did not respond in time might have finished since
+    let _ = REQUEST.compare_exchange(RECORDED, IDLE, Ordering::AcqRel, Ordering::Acquire);
+    if Instant::now() >= deadline
+        || REQUEST
+            .compare_exchange(IDLE, tid, Ordering::AcqRel, Ordering::Acquire)
+            .is_err()
+    {
+        return None;
+    }
+
+    let sent = unsafe { libc::syscall(libc::SYS_tgkill, libc::getpid(), tid, capture_signal()) };
+    if sent != 0 {
+        // the thread exited in the meantime
+        REQUEST.store(IDLE, Ordering::Release);
+        return None;
+    }
+
+    let thread_deadline = deadline.min(Instant::now() + THREAD_TIMEOUT);
+    while REQUEST.load(Ordering::Acquire) != RECORDED {
+        if Instant::now() >= thread_deadline {
+            // if the handler is recording already, the buffer is not used until it finished
+            let _ = REQUEST.compare_exchange(tid, IDLE, Ordering::AcqRel, Ordering::Acquire);
+            return None;
+        }
+        thread::sleep(Duration::from_micros(50));
+    }
+
+    let count = FRAME_COUNT.load(Ordering::Relaxed).min(MAX_FRAMES);
+    let addresses: Vec<(usize, usize)> = (0..count)
+        .map(|index| {
+            (
+                FRAME_ADDRESSES[index].load(Ordering::Relaxed),
+                SYMBOL_ADDRESSES[index].load(Ordering::Relaxed),
+            )
+        })
+        .collect();
+    REQUEST.store(IDLE, Ordering::Release);
+
+    // removes the frames of the handler and of the signal trampoline of the C library
+    let handler = handle_signal as *const () as usize;
+    let skip = addresses
+        .iter()
+        .position(|&(_, symbol_address)| symbol_address == handler)
+        .map_or(0, |index| (index + 2).min(addresses.len()));
+    Some(addresses[skip..].to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{capture, Thread};
+    use super::super::stacktrace::{self, Frame, Symbolication};
+    use serde_test::{assert_ser_tokens, Token};
+
+    fn resolve(addresses: &[(usize, usize)]) -> Vec<Frame> {
+        let in_project = |_: &str, _: &str| false;
+        stacktrace::resolve_addresses(addresses, &in_project, Symbolication::Immediate)
+    }
+
+    #[test]
+    fn test_thread_to_json() {
+        let frames = vec![Frame::new("test.rs", 400, "test", false)];
+        let thread = Thread::new("1", "main", true, &frames);
+
+        assert_ser_tokens(
+            &thread,
+            &[
+                Token::Struct {
+                    name: "Thread",
+                    len: 4,
+                },
+                Token::Str("id"),
+                Token::Str("1"),
+                Token::Str("name"),
+                Token::Str("main"),
+                Token::Str("errorReportingThread"),
+                Token::Bool(true),
+                Token::Str("stacktrace"),
+                Token::Seq { len: Some(1) },
+                Token::Struct {
+                    name: "Frame",
+                    len: 4,
+                },
+                Token::Str("file"),
+                Token::Str("test.rs"),
+                Token::Str("lineNumber"),
+                Token::U32(400),
+                Token::Str("method"),
+                Token::Str("test"),
+                Token::Str("inProject"),
+                Token::Bool(false),
+                Token::StructEnd,
+                Token::SeqEnd,
+                Token::StructEnd,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_capture_marks_calling_thread() {
+        let frames = vec![Frame::new("test.rs", 400, "test", false)];
+
+        let threads = capture(&frames, resolve);
+        let reporting: Vec<_> = threads
+            .iter()
+            .filter(|thread| thread.error_reporting_thread)
+            .collect();
+
+        assert_eq!(reporting.len(), 1);
+        assert_eq!(reporting[0].stacktrace.len(), 1);
+        assert_eq!(
+            Some(reporting[0].name.as_str()),
+            ::std::thread::current().name()
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_capture_all_threads() {
+        use std::sync::mpsc;
+        use std::thread;
+
+        let (started_tx, started_rx) = mpsc::channel();
+        let (stop_tx, stop_rx) = mpsc::channel::<()>();
+        let worker = thread::Builder::new()
+            .name("bugsnag-worker".to_owned())
+            .spawn(move || {
+                started_tx.send(()).unwrap();
+                let _ = stop_rx.recv();
+            })
+            .unwrap();
+        started_rx.recv().unwrap();
+
+        let threads = capture(&[], resolve);
+        drop(stop_tx);
+        worker.join().unwrap();
+
+        let worker = threads
+            .iter()
+            .find(|thread| thread.name == "bugsnag-worker")
+            .unwrap();
+        assert!(!worker.error_reporting_thread);
+        assert!(threads.len() >= 2);
+        // the worker is blocked in the closure of this test
+        assert!(
+            worker
+                .stacktrace
+                .iter()
+                .any(|frame| frame.method().contains("test_capture_all_threads"))
+        );
+        assert!(
+            !worker
+                .stacktrace
+                .iter()
+                .any(|frame| frame.method().contains("handle_signal"))
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_capture_thread_that_blocks_signals() {
+        use libc;
+        use std::{mem, ptr};
+        use std::sync::mpsc;
+        use std::thread;
+
+        let (started_tx, started_rx) = mpsc::channel();
+        let (stop_tx, stop_rx) = mpsc::channel::<()>();
+        let worker = thread::Builder::new()
+            .name("bugsnag-blocked".to_owned())
+            .spawn(move || {
+                unsafe {
+                    let mut signals: libc::sigset_t = mem::zeroed();
+                    libc::sigfillset(&mut signals);
+                    libc::pthread_sigmask(libc::SIG_BLOCK, &signals, ptr::null_mut());
+                }
+                started_tx.send(()).unwrap();
+                let _ = stop_rx.recv();
+            })
+            .unwrap();
+        started_rx.recv().unwrap();
+
+        let threads = capture(&[], resolve);
+        drop(stop_tx);
+        worker.join().unwrap();
+
+        let worker = threads
+            .iter()
+            .find(|thread| thread.name == "bugsnag-blocked")
+            .unwrap();
+        assert!(worker.stacktrace.is_empty());
+    }
+}
@@ -0,0 +1,67 @@
+//! Module for creating timestamps in the format expected by Bugsnag (ISO 8601, UTC).
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Returns the current time as ISO 8601 string.
+pub fn now() -> String {
+    format(SystemTime::now())
+}
+
+/// Formats the given time as ISO 8601 string with millisecond precision,
+/// e.g. `2018-03-01T12:30:00.000Z`.
+pub fn format(time: SystemTime) -> String {
+    let duration = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = duration.as_secs();
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let secs_of_day = secs % 86_400;
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60,
+        duration.subsec_millis()
+    )
+}
+
+/// Converts the days since the unix epoch into a (year, month, day) tuple.
+/// See http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = if z >= 0 { z } else { z - 146_096 } / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::format;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_format_epoch() {
+        assert_eq!(format(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+    }
+
+    #[test]
+    fn test_format() {
+        let time = UNIX_EPOCH + Duration::from_millis(1_519_907_400_123);
+        assert_eq!(format(time), "2018-03-01T12:30:00.123Z");
+    }
+
+    #[test]
+    fn test_format_leap_day() {
+        let time = UNIX_EPOCH + Duration::from_secs(951_782_400);
+        assert_eq!(format(time), "2000-02-29T00:00:00.000Z");
+    }
+}
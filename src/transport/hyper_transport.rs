@@ -0,0 +1,80 @@
+use std::io::Read;
+
+use hyper::Client;
+use hyper::header::Headers;
+use hyper::net::HttpsConnector;
+
+use super::tls::NativeTlsClient;
+use super::{parse_retry_after, Response, Transport};
+use super::super::Error;
+
+/// Transport using the hyper HTTP client. The client and its connection pool are
+/// shared by all notifications.
+pub struct HyperTransport {
+    /// The client, or the error of the TLS library if the TLS connector could not be created.
+    client: Result<Client, Error>,
+}
+
+impl HyperTransport {
+    /// Creates the transport with a TLS connector of the platform's TLS library. If the
+    /// connector can not be created, every notification fails with `Error::TlsInitFailed`,
+    /// use `try_new` to detect this when the transport is created.
+    pub fn new() -> HyperTransport {
+        let client = NativeTlsClient::new()
+            .map(|tls| Client::with_connector(HttpsConnector::new(tls)))
+            .map_err(|error| Error::TlsInitFailed(error.to_string()));
+
+        HyperTransport { client }
+    }
+
+    /// Creates the transport like `new`, but returns `Error::TlsInitFailed` if the TLS
+    /// connector can not be created.
+    pub fn try_new() -> Result<HyperTransport, Error> {
+        let transport = HyperTransport::new();
+        match transport.client {
+            Ok(_) => Ok(transport),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Creates the transport from a configured hyper client, e.g. to use a proxy.
+    pub fn with_client(client: Client) -> HyperTransport {
+        HyperTransport { client: Ok(client) }
+    }
+}
+
+impl Default for HyperTransport {
+    fn default() -> HyperTransport {
+        HyperTransport::new()
+    }
+}
+
+impl Transport for HyperTransport {
+    fn send(&self, url: &str, headers: &[(&str, &str)], body: &str) -> Result<Response, Error> {
+        let client = match self.client {
+            Ok(ref client) => client,
+            Err(ref error) => return Err(error.clone()),
+        };
+
+        let mut raw_headers = Headers::new();
+        for &(name, value) in headers {
+            raw_headers.set_raw(name.to_owned(), vec![value.as_bytes().to_vec()]);
+        }
+
+        match client.post(url).headers(raw_headers).body(body).send() {
+            Ok(mut response) => {
+                // read the body, so that the connection can be reused
+                let _ = response.read_to_end(&mut Vec::new());
+                let retry_after = response
+                    .headers
+                    .get_raw("Retry-After")
+                    .and_then(|values| values.first())
+                    .and_then(|value| ::std::str::from_utf8(value).ok())
+                    .and_then(parse_retry_after);
+
+                Ok(Response::new(response.status.to_u16()).with_retry_after(retry_after))
+            }
+            Err(_) => Err(Error::JsonTransferFailed),
+        }
+    }
+}
@@ -0,0 +1,178 @@
+//! Module for the transport that sends the json to Bugsnag.
+//!
+//! By default, the notifications are send with hyper. Other HTTP clients can be used
+//! by implementing the `Transport` trait or by enabling one of the cargo features
+//! `ureq-transport` and `reqwest-transport`.
+
+#[cfg(feature = "hyper-transport")]
+mod tls;
+#[cfg(feature = "hyper-transport")]
+mod hyper_transport;
+#[cfg(feature = "hyper-transport")]
+pub use self::hyper_transport::HyperTransport;
+#[cfg(feature = "ureq-transport")]
+mod ureq_transport;
+#[cfg(feature = "ureq-transport")]
+pub use self::ureq_transport::UreqTransport;
+#[cfg(feature = "reqwest-transport")]
+mod reqwest_transport;
+#[cfg(feature = "reqwest-transport")]
+pub use self::reqwest_transport::ReqwestTransport;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::Error;
+
+/// The response of Bugsnag to a delivered json.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    status: u16,
+    retry_after: Option<Duration>,
+}
+
+impl Response {
+    pub fn new(status: u16) -> Response {
+        Response {
+            status,
+            retry_after: None,
+        }
+    }
+
+    /// Sets the delay that Bugsnag requested with the `Retry-After` header.
+    pub fn with_retry_after(mut self, retry_after: Option<Duration>) -> Self {
+        self.retry_after = retry_after;
+        self
+    }
+
+    /// The http status code of the response.
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+
+    /// The delay that Bugsnag requested before the next delivery.
+    pub fn retry_after(&self) -> Option<Duration> {
+        self.retry_after
+    }
+}
+
+/// Parses the value of a `Retry-After` header. Only the delay in seconds is
+/// supported, not the http date.
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    value.trim().parse().ok().map(Duration::from_secs)
+}
+
+/// A transport delivers the json to Bugsnag.
+pub trait Transport: Send + Sync {
+    /// Sends `body` with the given headers in a POST request to `url`.
+    ///
+    /// The transport should only return an error if no response was received, e.g.
+    /// because of a network error. A response with an error status is still a response.
+    fn send(&self, url: &str, headers: &[(&str, &str)], body: &str) -> Result<Response, Error>;
+}
+
+impl<T: Transport + ?Sized> Transport for Arc<T> {
+    fn send(&self, url: &str, headers: &[(&str, &str)], body: &str) -> Result<Response, Error> {
+        (**self).send(url, headers, body)
+    }
+}
+
+/// The transport that is used if no HTTP client is enabled by the cargo features.
+struct MissingTransport;
+
+impl Transport for MissingTransport {
+    fn send(&self, _: &str, _: &[(&str, &str)], _: &str) -> Result<Response, Error> {
+        Err(Error::JsonTransferFailed)
+    }
+}
+
+/// Returns the transport of the first HTTP client that is enabled by the cargo features,
+/// in the order hyper, ureq and reqwest.
+#[allow(unreachable_code)]
+pub fn default_transport() -> Arc<dyn Transport> {
+    #[cfg(feature = "hyper-transport")]
+    {
+        return Arc::new(HyperTransport::new());
+    }
+    #[cfg(feature = "ureq-transport")]
+    {
+        return Arc::new(UreqTransport::new());
+    }
+    #[cfg(feature = "reqwest-transport")]
+    {
+        return Arc::new(ReqwestTransport::new());
+    }
+
+    Arc::new(MissingTransport)
+}
+
+/// A transport that records the requests instead of sending them. The transport
+/// answers with the queued responses and afterwards with the default status.
+#[cfg(test)]
+pub struct RecordingTransport {
+    status: u16,
+    responses: ::std::sync::Mutex<::std::collections::VecDeque<Result<Response, Error>>>,
+    requests: ::std::sync::Mutex<Vec<RecordedRequest>>,
+}
+
+#[cfg(test)]
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+#[cfg(test)]
+impl RecordingTransport {
+    pub fn new(status: u16) -> Arc<RecordingTransport> {
+        RecordingTransport::with_responses(status, Vec::new())
+    }
+
+    pub fn with_responses(
+        status: u16,
+        responses: Vec<Result<Response, Error>>,
+    ) -> Arc<RecordingTransport> {
+        Arc::new(RecordingTransport {
+            status,
+            responses: ::std::sync::Mutex::new(responses.into_iter().collect()),
+            requests: Default::default(),
+        })
+    }
+
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+impl Transport for RecordingTransport {
+    fn send(&self, url: &str, headers: &[(&str, &str)], body: &str) -> Result<Response, Error> {
+        self.requests.lock().unwrap().push(RecordedRequest {
+            url: url.to_owned(),
+            headers: headers
+                .iter()
+                .map(|&(name, value)| (name.to_owned(), value.to_owned()))
+                .collect(),
+            body: body.to_owned(),
+        });
+
+        match self.responses.lock().unwrap().pop_front() {
+            Some(response) => response,
+            None => Ok(Response::new(self.status)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_retry_after;
+    use std::time::Duration;
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(parse_retry_after("120"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_retry_after(" 5 "), Some(Duration::from_secs(5)));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), None);
+    }
+}
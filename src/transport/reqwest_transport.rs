@@ -0,0 +1,49 @@
+use reqwest;
+
+use super::{parse_retry_after, Response, Transport};
+use super::super::Error;
+
+/// Transport using the blocking reqwest HTTP client. The client and its connection
+/// pool are shared by all notifications.
+pub struct ReqwestTransport {
+    client: reqwest::blocking::Client,
+}
+
+impl ReqwestTransport {
+    pub fn new() -> ReqwestTransport {
+        ReqwestTransport::with_client(reqwest::blocking::Client::new())
+    }
+
+    /// Creates the transport from a configured reqwest client, e.g. to use a proxy.
+    pub fn with_client(client: reqwest::blocking::Client) -> ReqwestTransport {
+        ReqwestTransport { client }
+    }
+}
+
+impl Default for ReqwestTransport {
+    fn default() -> ReqwestTransport {
+        ReqwestTransport::new()
+    }
+}
+
+impl Transport for ReqwestTransport {
+    fn send(&self, url: &str, headers: &[(&str, &str)], body: &str) -> Result<Response, Error> {
+        let mut request = self.client.post(url).body(body.to_owned());
+        for &(name, value) in headers {
+            request = request.header(name, value);
+        }
+
+        match request.send() {
+            Ok(response) => {
+                let retry_after = response
+                    .headers()
+                    .get("Retry-After")
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(parse_retry_after);
+
+                Ok(Response::new(response.status().as_u16()).with_retry_after(retry_after))
+            }
+            Err(_) => Err(Error::JsonTransferFailed),
+        }
+    }
+}
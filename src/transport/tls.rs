@@ -0,0 +1,83 @@
+//! Module providing TLS support for the hyper client, so that notifications can be
+//! send to https endpoints.
+
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, SocketAddr};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use hyper;
+use hyper::net::{HttpStream, NetworkStream, SslClient};
+use native_tls::{self, HandshakeError, TlsConnector};
+
+/// A TLS stream that can be used by hyper. Hyper requires the stream to be `Clone`,
+/// so the stream is shared behind a mutex.
+#[derive(Clone)]
+pub struct TlsStream(Arc<Mutex<native_tls::TlsStream<HttpStream>>>);
+
+impl TlsStream {
+    fn lock(&self) -> MutexGuard<'_, native_tls::TlsStream<HttpStream>> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Read for TlsStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.lock().read(buf)
+    }
+}
+
+impl Write for TlsStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.lock().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.lock().flush()
+    }
+}
+
+impl NetworkStream for TlsStream {
+    fn peer_addr(&mut self) -> io::Result<SocketAddr> {
+        self.lock().get_mut().peer_addr()
+    }
+
+    fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        self.lock().get_ref().set_read_timeout(dur)
+    }
+
+    fn set_write_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        self.lock().get_ref().set_write_timeout(dur)
+    }
+
+    fn close(&mut self, how: Shutdown) -> io::Result<()> {
+        let mut stream = self.lock();
+        // the shutdown of the TLS session is best effort, the socket is closed anyway
+        let _ = stream.shutdown();
+        stream.get_mut().close(how)
+    }
+}
+
+/// Wraps the connections of hyper into TLS streams of the platform's TLS library.
+pub struct NativeTlsClient(TlsConnector);
+
+impl NativeTlsClient {
+    pub fn new() -> Result<NativeTlsClient, native_tls::Error> {
+        TlsConnector::new().map(NativeTlsClient)
+    }
+}
+
+impl SslClient for NativeTlsClient {
+    type Stream = TlsStream;
+
+    fn wrap_client(&self, stream: HttpStream, host: &str) -> hyper::Result<TlsStream> {
+        match self.0.connect(host, stream) {
+            Ok(stream) => Ok(TlsStream(Arc::new(Mutex::new(stream)))),
+            Err(HandshakeError::Failure(err)) => Err(hyper::Error::Ssl(Box::new(err))),
+            Err(HandshakeError::WouldBlock(_)) => Err(hyper::Error::Io(io::Error::new(
+                io::ErrorKind::WouldBlock,
+                "TLS handshake would block",
+            ))),
+        }
+    }
+}
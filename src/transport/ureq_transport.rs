@@ -0,0 +1,44 @@
+use ureq;
+
+use super::{parse_retry_after, Response, Transport};
+use super::super::Error;
+
+/// Transport using the ureq HTTP client. The agent and its connection pool are
+/// shared by all notifications.
+pub struct UreqTransport {
+    agent: ureq::Agent,
+}
+
+impl UreqTransport {
+    pub fn new() -> UreqTransport {
+        UreqTransport::with_agent(ureq::Agent::new())
+    }
+
+    /// Creates the transport from a configured ureq agent, e.g. to use a proxy.
+    pub fn with_agent(agent: ureq::Agent) -> UreqTransport {
+        UreqTransport { agent }
+    }
+}
+
+impl Default for UreqTransport {
+    fn default() -> UreqTransport {
+        UreqTransport::new()
+    }
+}
+
+impl Transport for UreqTransport {
+    fn send(&self, url: &str, headers: &[(&str, &str)], body: &str) -> Result<Response, Error> {
+        let mut request = self.agent.post(url);
+        for &(name, value) in headers {
+            request = request.set(name, value);
+        }
+
+        match request.send_string(body) {
+            Ok(response) | Err(ureq::Error::Status(_, response)) => {
+                let retry_after = response.header("Retry-After").and_then(parse_retry_after);
+                Ok(Response::new(response.status()).with_retry_after(retry_after))
+            }
+            Err(ureq::Error::Transport(_)) => Err(Error::JsonTransferFailed),
+        }
+    }
+}
@@ -0,0 +1,120 @@
+//! Module for the user that was affected by an error.
+//!
+//! The user can be set globally for a `Bugsnag` instance, for the current thread or for
+//! a single notification. The user of a notification takes precedence over the user of
+//! the current thread, which takes precedence over the user of the `Bugsnag` instance.
+//!
+//! # Example
+//!
+//! ```
+//! // e.g. at the beginning of a request in a web server
+//! bugsnag::user::set_thread_user(bugsnag::User::new(Some("user-id"), None, None));
+//!
+//! // ...
+//!
+//! bugsnag::user::reset_thread_user();
+//! ```
+
+use std::cell::RefCell;
+
+thread_local! {
+    static THREAD_USER: RefCell<Option<User>> = const { RefCell::new(None) };
+}
+
+/// Information about the user that was affected by an error.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct User {
+    #[serde(skip_serializing_if = "Option::is_none")] id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")] name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")] email: Option<String>,
+}
+
+impl User {
+    pub fn new(id: Option<&str>, name: Option<&str>, email: Option<&str>) -> User {
+        User {
+            id: id.map(|v| v.to_owned()),
+            name: name.map(|v| v.to_owned()),
+            email: email.map(|v| v.to_owned()),
+        }
+    }
+}
+
+/// Sets the user for all notifications that are send from the current thread.
+pub fn set_thread_user(user: User) {
+    THREAD_USER.with(|current| *current.borrow_mut() = Some(user));
+}
+
+/// Removes the user of the current thread.
+pub fn reset_thread_user() {
+    THREAD_USER.with(|current| *current.borrow_mut() = None);
+}
+
+/// Returns the user of the current thread.
+pub fn thread_user() -> Option<User> {
+    THREAD_USER.with(|current| current.borrow().clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{reset_thread_user, set_thread_user, thread_user, User};
+    use serde_test::{assert_ser_tokens, Token};
+    use std::thread;
+
+    #[test]
+    fn test_user_to_json() {
+        let user = User::new(Some("123"), Some("Jane"), Some("jane@example.com"));
+
+        assert_ser_tokens(
+            &user,
+            &[
+                Token::Struct {
+                    name: "User",
+                    len: 3,
+                },
+                Token::Str("id"),
+                Token::Some,
+                Token::Str("123"),
+                Token::Str("name"),
+                Token::Some,
+                Token::Str("Jane"),
+                Token::Str("email"),
+                Token::Some,
+                Token::Str("jane@example.com"),
+                Token::StructEnd,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_user_with_id_to_json() {
+        let user = User::new(Some("123"), None, None);
+
+        assert_ser_tokens(
+            &user,
+            &[
+                Token::Struct {
+                    name: "User",
+                    len: 1,
+                },
+                Token::Str("id"),
+                Token::Some,
+                Token::Str("123"),
+                Token::StructEnd,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_thread_user() {
+        set_thread_user(User::new(Some("123"), None, None));
+
+        thread::spawn(|| assert_eq!(thread_user(), None))
+            .join()
+            .unwrap();
+        assert_eq!(thread_user(), Some(User::new(Some("123"), None, None)));
+
+        reset_thread_user();
+        assert_eq!(thread_user(), None);
+    }
+}